@@ -0,0 +1,54 @@
+use redismodule_cmd::{
+    ArgType, Collection, Command, DistanceUnit, Duplicates, KwargPolicy, LexBound, Longitude,
+    MemorySize, Set, StreamId, Value,
+};
+
+#[cfg_attr(feature = "docgen", redismodule_cmd::rediscmd_doc)]
+pub fn hello_foo() -> Command {
+    command! {
+        name: "hello.foo",
+        desc: "hello command example",
+        args: [
+            ["input", "a string", ArgType::Arg, String, Collection::Unit, None],
+            ["optional", "another string", ArgType::Arg, String, Collection::Unit, Some(Box::new("baz".to_owned()))],
+            ["n", "some number", ArgType::Kwarg, u64, Collection::Unit, Some(Box::new(1_u64))],
+            ["vec1", "a vector of ints", ArgType::Kwarg, i64, Collection::Vec, None],
+        ],
+        examples: [
+            "hello.foo bar vec1 3 1 1 1",
+            "hello.foo bar qux n 2 vec1 0",
+            ["hello.foo bar", Err],
+            ["hello.foo bar n 2 3", Err("Unexpected arg 3")],
+        ],
+    }
+}
+
+#[cfg_attr(feature = "docgen", redismodule_cmd::rediscmd_doc)]
+pub fn hello_bar() -> Command {
+    command! {
+        name: "hello.bar",
+        desc: "collections and data types example",
+        kwarg_policy: KwargPolicy::Terminator,
+        args: [
+            ["key", "a key", ArgType::Arg, String, Collection::Unit, None],
+            ["point", "a point", ArgType::Arg, f64, Collection::Array(2), None],
+            ["scale", "a scale", ArgType::Arg, f64, Collection::Unit, Some(Box::new(1.5))],
+            ["unit", "a unit", ArgType::Kwarg, DistanceUnit, Collection::Unit, Some(Box::new(DistanceUnit::Km))],
+            ["limit_offset", "an offset", ArgType::Kwarg, u64, Collection::Unit, Some(Box::new(0_u64)), token: "limit"],
+            ["limit_count", "a count", ArgType::Kwarg, i64, Collection::Unit, Some(Box::new(-1_i64)), token: "limit"],
+            ["tags", "some tags", ArgType::Kwarg, String, Collection::Set, Some(Box::new(Set::default())), duplicates: Duplicates::Reject],
+            ["attrs", "some attributes", ArgType::Kwarg, u64, Collection::Map(String), Some(Box::new(vec![(Box::new("size".to_owned()) as Box<dyn Value>, Box::new(1_u64) as Box<dyn Value>)])), max_len: 10],
+            ["weights", "some weights", ArgType::Kwarg, i64, Collection::Vec, Some(Box::new(vec![Box::new(1_i64) as Box<dyn Value>, Box::new(-2_i64)]))],
+            ["since", "a stream ID", ArgType::Kwarg, StreamId, Collection::Unit, Some(Box::new(StreamId::Min))],
+            ["upto", "a lexicographical bound", ArgType::Kwarg, LexBound, Collection::Unit, Some(Box::new(LexBound::PosInfinity))],
+            ["maxmem", "a memory limit", ArgType::Kwarg, MemorySize, Collection::Unit, Some(Box::new(MemorySize(1048576)))],
+            ["lon", "a longitude", ArgType::Kwarg, Longitude, Collection::Unit, Some(Box::new(Longitude(13.4)))],
+            ["labels", "some labels", ArgType::Kwarg, String, Collection::Set, Some(Box::new(Set { values: vec![Box::new("a".to_owned()) as Box<dyn Value>], duplicates: false }))],
+        ],
+        examples: [
+            "hello.bar key 1.5 2 3 tags 2 a b",
+            "hello.bar key 1.5 2 -- limit 10 20 attrs 1 size 3 unit mi",
+            ["hello.bar key 1.5 2 -- tags 2 a a", Err("tags has a duplicate value a")],
+        ],
+    }
+}
@@ -0,0 +1,33 @@
+#[macro_use]
+extern crate redismodule_cmd;
+
+use redismodule_cmd::docgen;
+
+mod commands;
+
+/// Writes the command reference of the hello example, and removes the
+/// sections `rediscmd_doc` wrote for commands that are gone, e.g.
+/// `cargo run --example docgen`
+fn main() -> std::io::Result<()> {
+    let cmds = vec![commands::hello_foo(), commands::hello_bar()];
+
+    docgen::write_reference("doc/COMMAND_REFERENCE_GEN.md", &cmds)?;
+    docgen::remove_stale_sections("doc/commands", &cmds)
+}
+
+#[cfg(all(test, feature = "docgen"))]
+mod tests {
+    use super::commands;
+    use std::fs;
+
+    /// The sections `rediscmd_doc` wrote when compiling this example match
+    /// the runtime reference, for defaults of all forms it renders like it.
+    #[test]
+    fn rediscmd_doc_test() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/doc/commands");
+        for cmd in vec![commands::hello_foo(), commands::hello_bar()] {
+            let section = fs::read_to_string(format!("{}/{}.md", dir, cmd.name)).unwrap();
+            assert_eq!(section, cmd.to_markdown());
+        }
+    }
+}
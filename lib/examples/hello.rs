@@ -5,20 +5,13 @@ extern crate redis_module;
 extern crate redismodule_cmd;
 
 use redis_module::{Context, RedisError, RedisResult, RedisValue};
-use redismodule_cmd::{rediscmd_doc, ArgType, Collection, Command};
+use redismodule_cmd::Command;
+
+mod commands;
 
 thread_local! {
-    #[rediscmd_doc(clean)]
-    static CMD: Command = command!{
-        name: "hello.foo",
-        desc: "hello command example",
-        args: [
-            ["input", "a string", ArgType::Arg, String, Collection::Unit, None],
-            ["optional", "another string", ArgType::Arg, String, Collection::Unit, Some(Box::new("baz".to_owned()))],
-            ["n", "some number", ArgType::Kwarg, u64, Collection::Unit, Some(Box::new(1_u64))],
-            ["vec1", "a vector of ints", ArgType::Kwarg, i64, Collection::Vec, None],
-        ],
-    };
+    static CMD: Command = commands::hello_foo();
+    static CMD_BAR: Command = commands::hello_bar();
 }
 
 fn hello_foo(_: &Context, args: Vec<String>) -> RedisResult {
@@ -39,6 +32,25 @@ fn hello_foo(_: &Context, args: Vec<String>) -> RedisResult {
     return Ok(response.into());
 }
 
+fn hello_bar(_: &Context, args: Vec<String>) -> RedisResult {
+    let mut parsed = CMD_BAR.with(|cmd| cmd.parse_args(args))?;
+
+    let key = parsed.remove("key").unwrap().as_string()?;
+    let [x, y] = parsed.remove("point").unwrap().as_array::<f64, 2>()?;
+    let scale = parsed.remove("scale").unwrap().as_f64()?;
+    let tags = parsed.remove("tags").unwrap().as_hash_set::<String>()?;
+    let attrs = parsed.remove("attrs").unwrap().as_map::<String, u64>()?;
+
+    let response: Vec<RedisValue> = vec![
+        key.into(),
+        format!("{} {}", x * scale, y * scale).into(),
+        (tags.len() as i64).into(),
+        (attrs.values().sum::<u64>() as i64).into(),
+    ];
+
+    Ok(response.into())
+}
+
 //////////////////////////////////////////////////////
 
 redis_module! {
@@ -47,6 +59,7 @@ redis_module! {
     data_types: [],
     commands: [
         ["hello.foo", hello_foo, "", 0, 0, 0],
+        ["hello.bar", hello_bar, "", 0, 0, 0],
     ],
 }
 
@@ -64,6 +77,53 @@ mod tests {
         )
     }
 
+    fn run_hello_bar(args: &[&str]) -> RedisResult {
+        hello_bar(
+            &Context::dummy(),
+            args.iter().map(|v| String::from(*v)).collect(),
+        )
+    }
+
+    #[test]
+    fn hello_foo_examples() {
+        CMD.with(|cmd| cmd.check_examples()).unwrap();
+    }
+
+    #[test]
+    fn hello_bar_examples() {
+        CMD_BAR.with(|cmd| cmd.check_examples()).unwrap();
+    }
+
+    #[test]
+    fn hello_bar_valid_args() {
+        let result = run_hello_bar(&[
+            "hello.bar",
+            "k",
+            "1",
+            "2",
+            "3",
+            "ATTRS",
+            "2",
+            "a",
+            "1",
+            "b",
+            "2",
+        ]);
+
+        match result {
+            Ok(RedisValue::Array(v)) => {
+                let exp = vec![
+                    RedisValue::BulkString("k".to_owned()),
+                    RedisValue::BulkString("3 6".to_owned()),
+                    RedisValue::Integer(0),
+                    RedisValue::Integer(3),
+                ];
+                assert_eq!(v, exp);
+            }
+            _ => assert!(false, "Bad result: {:?}", result),
+        }
+    }
+
     #[test]
     fn hello_foo_valid_args() {
         let result = run_hello_foo(&vec![
@@ -0,0 +1,676 @@
+//! Generation of typed client side builders for commands, so that callers of
+//! a module build their args from the same definitions the module parses
+//! them with. Like `docgen`, meant to be called from a small binary or a
+//! `build.rs` that has access to all commands of a module.
+
+use std::any::type_name;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use itertools::Itertools;
+
+use crate::docgen::write_if_changed;
+use crate::{
+    raw_arg, Arg, Collection, Command, DistanceUnit, KwargPolicy, Latitude, LexBound, Longitude,
+    MemorySize, Milliseconds, ScoreBound, Seconds, StreamId,
+};
+
+/// What the generated builders produce.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Output {
+    /// `into_args`, returning the raw args as `Vec<Vec<u8>>`, or an error
+    /// if the args can't be given as raw args.
+    Args,
+    /// `into_args`, as well as `into_cmd` returning a `redis::Cmd`. The
+    /// generated code then needs the `redis` crate.
+    RedisCmd,
+}
+
+const HEADER: &str = "// Generated by redismodule_cmd from the command definitions. Do not edit.\n";
+
+impl Command {
+    /// Renders the rust source of a typed builder for this command. Required
+    /// args are passed to `new`, optional args and kwargs are set with
+    /// methods named after them. The builder serializes the args in the
+    /// order and format `parse_args` expects, failing like `to_argv` where
+    /// that isn't possible, and for durations that aren't a whole number of
+    /// milliseconds. Args of the data types in `types` have those types in
+    /// the builder, so the generated code needs `redismodule_cmd`, and
+    /// `serde_json` for JSON args.
+    pub fn to_client_builder(&self, output: Output) -> String {
+        let name = struct_name(self.name);
+        let required: Vec<&Arg> = self
+            .required_args
+            .iter()
+            .chain(self.doc_kwargs().filter(|a| a.default.is_none()))
+            .collect();
+        let optional: Vec<&Arg> = self
+            .optional_args
+            .iter()
+            .chain(self.doc_kwargs().filter(|a| a.default.is_some()))
+            .collect();
+
+        let mut src = String::new();
+        writeln!(src, "/// {}\n///\n/// `{}`", self.desc, self).unwrap();
+        writeln!(src, "#[derive(Debug, Clone)]\npub struct {} {{", name).unwrap();
+        for arg in required.iter() {
+            writeln!(src, "    {}: {},", field_name(arg.arg), arg.rust_type()).unwrap();
+        }
+        for arg in optional.iter() {
+            writeln!(
+                src,
+                "    {}: Option<{}>,",
+                field_name(arg.arg),
+                arg.rust_type()
+            )
+            .unwrap();
+        }
+        writeln!(src, "}}\n\nimpl {} {{", name).unwrap();
+
+        // constructor taking the required args
+        let params = required
+            .iter()
+            .map(|a| format!("{}: {}", field_name(a.arg), a.rust_type()))
+            .join(", ");
+        writeln!(src, "    pub fn new({}) -> Self {{", params).unwrap();
+        writeln!(src, "        {} {{", name).unwrap();
+        for arg in required.iter() {
+            writeln!(src, "            {},", field_name(arg.arg)).unwrap();
+        }
+        for arg in optional.iter() {
+            writeln!(src, "            {}: None,", field_name(arg.arg)).unwrap();
+        }
+        writeln!(src, "        }}\n    }}").unwrap();
+
+        // setters of optional args and kwargs
+        for arg in optional.iter() {
+            let field = field_name(arg.arg);
+            writeln!(src, "\n    /// {}", arg.desc).unwrap();
+            writeln!(
+                src,
+                "    pub fn {field}(mut self, {field}: {ty}) -> Self {{
+        self.{field} = Some({field});
+        self
+    }}",
+                field = field,
+                ty = arg.rust_type()
+            )
+            .unwrap();
+        }
+
+        // serialization
+        writeln!(
+            src,
+            "\n    /// Serializes the args in the order and format `{}` expects.",
+            self.name
+        )
+        .unwrap();
+        writeln!(
+            src,
+            "    pub fn into_args(self) -> Result<Vec<Vec<u8>>, String> {{"
+        )
+        .unwrap();
+        writeln!(src, "        let mut args: Vec<Vec<u8>> = Vec::new();").unwrap();
+        writeln!(
+            src,
+            "        args.push({:?}.as_bytes().to_vec());",
+            self.name
+        )
+        .unwrap();
+        for arg in self.required_args.iter() {
+            src.push_str(&arg.push_value(&format!("self.{}", field_name(arg.arg)), 2));
+        }
+        if !self.optional_args.is_empty() {
+            // earlier optional args are filled with their defaults when a
+            // later one is set, since they are matched by position. Unless
+            // keywords are matched greedily, all of them are given so that
+            // kwargs are recognized.
+            let values = self
+                .optional_args
+                .iter()
+                .map(|a| {
+                    format!(
+                        "match self.{} {{ Some(v) => Some({}), None => None }}",
+                        field_name(a.arg),
+                        a.raw_arg_expr("v")
+                    )
+                })
+                .join(", ");
+            let defaults = self
+                .optional_args
+                .iter()
+                .map(|a| format!("{:?}", raw_arg(&**a.default.as_ref().unwrap())))
+                .join(", ");
+            writeln!(
+                src,
+                "        let optional: Vec<Option<Vec<u8>>> = vec![{values}];
+        let defaults = [{defaults}];",
+                values = values,
+                defaults = defaults
+            )
+            .unwrap();
+            if self.kwarg_policy != KwargPolicy::Greedy {
+                src.push_str(
+                    "        for (val, default) in optional.into_iter().zip(defaults.iter()) {
+            args.push(val.unwrap_or_else(|| default.as_bytes().to_vec()));
+        }
+",
+                );
+            } else if self.keywords.is_empty() {
+                src.push_str(
+                    "        if let Some(last) = optional.iter().rposition(Option::is_some) {
+            for (val, default) in optional.into_iter().zip(defaults.iter()).take(last + 1) {
+                args.push(val.unwrap_or_else(|| default.as_bytes().to_vec()));
+            }
+        }
+",
+                );
+            } else {
+                // a value matching a keyword would end the optional args
+                let names = self.optional_args.iter().map(|a| format!("{:?}", a.arg));
+                writeln!(
+                    src,
+                    "        let names = [{names}];
+        let keywords = [{keywords}];
+        if let Some(last) = optional.iter().rposition(Option::is_some) {{
+            for ((val, default), name) in optional.into_iter().zip(defaults.iter()).zip(names.iter()).take(last + 1) {{
+                let val = val.unwrap_or_else(|| default.as_bytes().to_vec());
+                if keywords.iter().any(|k| k.as_bytes().eq_ignore_ascii_case(&val)) {{
+                    return Err(format!(\"{{}} of {{}} can't be given as a raw arg\", String::from_utf8_lossy(&val), name));
+                }}
+                args.push(val);
+            }}
+        }}",
+                    names = names.format(", "),
+                    keywords = self.keywords.keys().sorted().map(|k| format!("{:?}", k)).format(", ")
+                )
+                .unwrap();
+            }
+        }
+        for (keyword, args) in self.keyword_groups() {
+            let keyword = format!(
+                "args.push({:?}.as_bytes().to_vec());\n",
+                keyword.to_uppercase()
+            );
+            if args[0].default.is_none() {
+                write!(src, "        {}", keyword).unwrap();
+                for arg in args {
+                    src.push_str(&arg.push_value(&format!("self.{}", field_name(arg.arg)), 2));
+                }
+            } else if args.len() == 1 {
+                let field = field_name(args[0].arg);
+                writeln!(src, "        if let Some({0}) = self.{0} {{", field).unwrap();
+                write!(src, "            {}", keyword).unwrap();
+                src.push_str(&args[0].push_value(&field, 3));
+                writeln!(src, "        }}").unwrap();
+            } else {
+                // kwargs sharing a keyword are given together, with the
+                // defaults of those that aren't set
+                let set = args
+                    .iter()
+                    .map(|a| format!("self.{}.is_some()", field_name(a.arg)))
+                    .join(" || ");
+                writeln!(src, "        if {} {{", set).unwrap();
+                write!(src, "            {}", keyword).unwrap();
+                for arg in args {
+                    let mut default = Vec::new();
+                    arg.push_raw_value(&**arg.default.as_ref().unwrap(), &mut default)
+                        .unwrap();
+                    writeln!(
+                        src,
+                        "            match self.{field} {{
+                Some({field}) => {{
+{push}                }}
+                None => args.extend({default:?}.iter().map(|d| d.as_bytes().to_vec())),
+            }}",
+                        field = field_name(arg.arg),
+                        push = arg.push_value(&field_name(arg.arg), 5),
+                        default = default
+                    )
+                    .unwrap();
+                }
+                writeln!(src, "        }}").unwrap();
+            }
+        }
+        writeln!(src, "        Ok(args)\n    }}").unwrap();
+
+        if output == Output::RedisCmd {
+            writeln!(
+                src,
+                "
+    /// Builds a `redis::Cmd` from the args.
+    pub fn into_cmd(self) -> Result<redis::Cmd, String> {{
+        let mut cmd = redis::Cmd::new();
+        for arg in self.into_args()? {{
+            cmd.arg(arg);
+        }}
+        Ok(cmd)
+    }}"
+            )
+            .unwrap();
+        }
+        writeln!(src, "}}").unwrap();
+
+        src
+    }
+
+    /// Kwargs in alphabetical order of their keywords.
+    fn doc_kwargs(&self) -> impl Iterator<Item = &Arg> {
+        self.keyword_groups().flat_map(|(_, args)| args)
+    }
+}
+
+impl Arg {
+    /// Rust type of the arg in the generated builder.
+    fn rust_type(&self) -> String {
+        match self.kind {
+            Collection::Unit => self.value_type().to_owned(),
+            // sets are deduplicated by the module, and may hold floats
+            Collection::Vec | Collection::Set => format!("Vec<{}>", self.value_type()),
+            Collection::Array(len) => format!("[{}; {}]", self.value_type(), len),
+            // pairs rather than a map, as keys may be floats
+            Collection::Map(key_type) => {
+                format!("Vec<({}, {})>", rust_type(key_type), self.value_type())
+            }
+        }
+    }
+
+    /// Rust type of a single value of the arg.
+    fn value_type(&self) -> &'static str {
+        rust_type(self.type_name)
+    }
+
+    /// Expression turning the single value `expr` of the arg into a raw arg.
+    fn raw_arg_expr(&self, expr: &str) -> String {
+        raw_expr(self.value_type(), expr, self.arg)
+    }
+
+    /// Statements pushing the value(s) of the arg held by `expr`, with `Vec`s,
+    /// sets and maps prefixed by their count.
+    fn push_value(&self, expr: &str, indent: usize) -> String {
+        let pad = "    ".repeat(indent);
+        match self.kind {
+            Collection::Unit => format!("{}args.push({});\n", pad, self.raw_arg_expr(expr)),
+            // loops rather than closures, as turning values into raw args
+            // may return an error
+            Collection::Vec | Collection::Set => format!(
+                "{pad}args.push({expr}.len().to_string().into_bytes());
+{pad}for v in {expr} {{
+{pad}    args.push({to_raw});
+{pad}}}\n",
+                pad = pad,
+                expr = expr,
+                to_raw = self.raw_arg_expr("v")
+            ),
+            // arrays are iterated by value in any edition
+            Collection::Array(_) => format!(
+                "{pad}for v in IntoIterator::into_iter({expr}) {{
+{pad}    args.push({to_raw});
+{pad}}}\n",
+                pad = pad,
+                expr = expr,
+                to_raw = self.raw_arg_expr("v")
+            ),
+            Collection::Map(key_type) => format!(
+                "{pad}args.push({expr}.len().to_string().into_bytes());
+{pad}for (k, v) in {expr} {{
+{pad}    args.push({key_to_raw});
+{pad}    args.push({to_raw});
+{pad}}}\n",
+                pad = pad,
+                expr = expr,
+                key_to_raw = raw_expr(rust_type(key_type), "k", self.arg),
+                to_raw = self.raw_arg_expr("v")
+            ),
+        }
+    }
+}
+
+/// Rust type of a single value of a data type in the generated builder.
+/// Data types with a plain counterpart are passed as that, e.g. memory sizes
+/// as bytes, others as themselves.
+fn rust_type(name: &str) -> &'static str {
+    match name {
+        n if n == type_name::<u64>() || n == type_name::<MemorySize>() => "u64",
+        n if n == type_name::<i64>() => "i64",
+        n if n == type_name::<f64>()
+            || n == type_name::<Longitude>()
+            || n == type_name::<Latitude>() =>
+        {
+            "f64"
+        }
+        n if n == type_name::<Seconds>() || n == type_name::<Milliseconds>() => {
+            "std::time::Duration"
+        }
+        n if n == type_name::<ScoreBound>() => "redismodule_cmd::ScoreBound",
+        n if n == type_name::<LexBound>() => "redismodule_cmd::LexBound",
+        n if n == type_name::<StreamId>() => "redismodule_cmd::StreamId",
+        n if n == type_name::<DistanceUnit>() => "redismodule_cmd::DistanceUnit",
+        #[cfg(feature = "json")]
+        n if n == type_name::<serde_json::Value>() => "serde_json::Value",
+        _ => "String",
+    }
+}
+
+/// Expression turning `expr` of the rust type `ty` into a raw arg of the
+/// arg named `arg`.
+fn raw_expr(ty: &str, expr: &str, arg: &str) -> String {
+    match ty {
+        "String" => format!("{}.into_bytes()", expr),
+        "u64" | "i64" | "f64" => format!("{}.to_string().into_bytes()", expr),
+        // durations are given in milliseconds, which can't hold any less
+        "std::time::Duration" => format!(
+            "if {e}.subsec_nanos() % 1_000_000 == 0 {{ format!(\"{{}}ms\", {e}.as_millis()).into_bytes() }} else {{ return Err(format!(\"{{:?}} of {arg} isn't a whole number of milliseconds\", {e})); }}",
+            e = expr,
+            arg = arg
+        ),
+        _ => format!(
+            "redismodule_cmd::types::RawArg::raw(&{}).into_bytes()",
+            expr
+        ),
+    }
+}
+
+/// Name of the builder struct, e.g. `HelloFoo` for `hello.foo`.
+fn struct_name(cmd: &str) -> String {
+    cmd.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            let mut chars = s.chars();
+            match chars.next() {
+                Some(c) => c.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// Name of the field and setter of an arg, escaping rust keywords.
+fn field_name(arg: &str) -> String {
+    const KEYWORDS: &[&str] = &[
+        "as", "async", "await", "box", "break", "const", "continue", "dyn", "else", "enum",
+        "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+        "mut", "pub", "ref", "return", "static", "struct", "trait", "true", "try", "type",
+        "unsafe", "use", "where", "while", "yield",
+    ];
+
+    let name: String = arg
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if KEYWORDS.contains(&name.as_str()) {
+        format!("r#{}", name)
+    } else {
+        name
+    }
+}
+
+/// Renders the builders of all commands, sorted by command name.
+pub fn client_builders(cmds: &[Command], output: Output) -> String {
+    let mut src = HEADER.to_owned();
+    for cmd in cmds.iter().sorted_by_key(|c| c.name) {
+        src.push('\n');
+        src.push_str(&cmd.to_client_builder(output));
+    }
+
+    src
+}
+
+/// Writes the builders of all commands to `path`, e.g. a file the client
+/// crate `include!`s. Like `docgen::write_reference`, the file is replaced
+/// atomically and left untouched if unchanged.
+pub fn write_client_builders<P: AsRef<Path>>(
+    path: P,
+    cmds: &[Command],
+    output: Output,
+) -> io::Result<()> {
+    let path = path.as_ref();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    write_if_changed(path, &client_builders(cmds, output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{client_builders, struct_name, Output};
+    use crate::{ArgType, Collection, KwargPolicy, ScoreBound, Seconds, StreamId};
+    use std::time::Duration;
+
+    #[test]
+    fn struct_name_test() {
+        assert_eq!(struct_name("hello.foo"), "HelloFoo");
+        assert_eq!(struct_name("json_set"), "JsonSet");
+    }
+
+    #[test]
+    fn to_client_builder_test() {
+        let cmd = command! {
+            name: "hello.foo",
+            desc: "hello command example",
+            args: [
+                ["input", "a string", ArgType::Arg, String, Collection::Unit, None],
+                ["x", "a float", ArgType::Arg, f64, Collection::Unit, Some(Box::new(0.5_f64))],
+                ["type", "a string", ArgType::Arg, String, Collection::Unit, Some(Box::new("a".to_owned()))],
+                ["n", "some number", ArgType::Kwarg, u64, Collection::Unit, Some(Box::new(1_u64))],
+                ["vec1", "a vector of ints", ArgType::Kwarg, i64, Collection::Vec, None],
+            ],
+        };
+
+        let exp = r#"/// hello command example
+///
+/// `HELLO.FOO input [x] [type] [N n] VEC1 count vec1 [vec1 ...]`
+#[derive(Debug, Clone)]
+pub struct HelloFoo {
+    input: String,
+    vec1: Vec<i64>,
+    x: Option<f64>,
+    r#type: Option<String>,
+    n: Option<u64>,
+}
+
+impl HelloFoo {
+    pub fn new(input: String, vec1: Vec<i64>) -> Self {
+        HelloFoo {
+            input,
+            vec1,
+            x: None,
+            r#type: None,
+            n: None,
+        }
+    }
+
+    /// a float
+    pub fn x(mut self, x: f64) -> Self {
+        self.x = Some(x);
+        self
+    }
+
+    /// a string
+    pub fn r#type(mut self, r#type: String) -> Self {
+        self.r#type = Some(r#type);
+        self
+    }
+
+    /// some number
+    pub fn n(mut self, n: u64) -> Self {
+        self.n = Some(n);
+        self
+    }
+
+    /// Serializes the args in the order and format `hello.foo` expects.
+    pub fn into_args(self) -> Result<Vec<Vec<u8>>, String> {
+        let mut args: Vec<Vec<u8>> = Vec::new();
+        args.push("hello.foo".as_bytes().to_vec());
+        args.push(self.input.into_bytes());
+        let optional: Vec<Option<Vec<u8>>> = vec![match self.x { Some(v) => Some(v.to_string().into_bytes()), None => None }, match self.r#type { Some(v) => Some(v.into_bytes()), None => None }];
+        let defaults = ["0.5", "a"];
+        let names = ["x", "type"];
+        let keywords = ["n", "vec1"];
+        if let Some(last) = optional.iter().rposition(Option::is_some) {
+            for ((val, default), name) in optional.into_iter().zip(defaults.iter()).zip(names.iter()).take(last + 1) {
+                let val = val.unwrap_or_else(|| default.as_bytes().to_vec());
+                if keywords.iter().any(|k| k.as_bytes().eq_ignore_ascii_case(&val)) {
+                    return Err(format!("{} of {} can't be given as a raw arg", String::from_utf8_lossy(&val), name));
+                }
+                args.push(val);
+            }
+        }
+        if let Some(n) = self.n {
+            args.push("N".as_bytes().to_vec());
+            args.push(n.to_string().into_bytes());
+        }
+        args.push("VEC1".as_bytes().to_vec());
+        args.push(self.vec1.len().to_string().into_bytes());
+        for v in self.vec1 {
+            args.push(v.to_string().into_bytes());
+        }
+        Ok(args)
+    }
+}
+"#;
+        assert_eq!(cmd.to_client_builder(Output::Args), exp);
+
+        let src = cmd.to_client_builder(Output::RedisCmd);
+        assert!(src.contains("pub fn into_cmd(self) -> Result<redis::Cmd, String> {"));
+
+        // all optional args are given unless keywords are matched greedily
+        let src = cmd
+            .kwarg_policy(KwargPolicy::Terminator)
+            .to_client_builder(Output::Args);
+        assert!(!src.contains("rposition"));
+        assert!(!src.contains("keywords"));
+        assert!(src.contains("for (val, default) in optional.into_iter().zip(defaults.iter()) {"));
+    }
+
+    #[test]
+    fn keyword_group_test() {
+        let cmd = command! {
+            name: "test",
+            desc: "test desc",
+            args: [
+                ["limit_offset", "an offset", ArgType::Kwarg, u64, Collection::Unit, Some(Box::new(0_u64)), token: "limit"],
+                ["limit_count", "a count", ArgType::Kwarg, u64, Collection::Unit, Some(Box::new(10_u64)), token: "limit"],
+            ],
+        };
+
+        let src = cmd.to_client_builder(Output::Args);
+        assert!(src.contains(
+            "        if self.limit_offset.is_some() || self.limit_count.is_some() {
+            args.push(\"LIMIT\".as_bytes().to_vec());
+            match self.limit_offset {
+                Some(limit_offset) => {
+                    args.push(limit_offset.to_string().into_bytes());
+                }
+                None => args.extend([\"0\"].iter().map(|d| d.as_bytes().to_vec())),
+            }"
+        ));
+    }
+
+    #[test]
+    fn duration_test() {
+        let cmd = command! {
+            name: "test",
+            desc: "test desc",
+            args: [
+                ["ex", "a ttl", ArgType::Kwarg, Seconds, Collection::Unit, Some(Box::new(Seconds(Duration::from_secs(10))))],
+            ],
+        };
+
+        let src = cmd.to_client_builder(Output::Args);
+        assert!(src.contains("    ex: Option<std::time::Duration>,\n"));
+        // durations the module can't take are rejected rather than truncated
+        assert!(src.contains(
+            "args.push(if ex.subsec_nanos() % 1_000_000 == 0 { format!(\"{}ms\", ex.as_millis()).into_bytes() } else { return Err(format!(\"{:?} of ex isn't a whole number of milliseconds\", ex)); });"
+        ));
+    }
+
+    #[test]
+    fn data_type_test() {
+        let cmd = command! {
+            name: "test",
+            desc: "test desc",
+            args: [
+                ["since", "a stream ID", ArgType::Kwarg, StreamId, Collection::Unit, None],
+                ["bounds", "a range", ArgType::Kwarg, ScoreBound, Collection::Array(2), None],
+            ],
+        };
+
+        let src = cmd.to_client_builder(Output::Args);
+        assert!(src.contains("    bounds: [redismodule_cmd::ScoreBound; 2],\n"));
+        assert!(src.contains("    since: redismodule_cmd::StreamId,\n"));
+        assert!(src.contains(
+            "        args.push(\"SINCE\".as_bytes().to_vec());
+        args.push(redismodule_cmd::types::RawArg::raw(&self.since).into_bytes());
+"
+        ));
+    }
+
+    #[test]
+    fn map_test() {
+        let cmd = command! {
+            name: "test",
+            desc: "test desc",
+            args: [
+                ["attrs", "some attributes", ArgType::Arg, Seconds, Collection::Map(String), None],
+            ],
+        };
+
+        let src = cmd.to_client_builder(Output::Args);
+        assert!(src.contains("    attrs: Vec<(String, std::time::Duration)>,\n"));
+        assert!(src.contains(
+            "        args.push(self.attrs.len().to_string().into_bytes());
+        for (k, v) in self.attrs {
+            args.push(k.into_bytes());
+            args.push(if v.subsec_nanos() % 1_000_000 == 0 { format!(\"{}ms\", v.as_millis()).into_bytes() } else { return Err(format!(\"{:?} of attrs isn't a whole number of milliseconds\", v)); });
+        }
+"
+        ));
+    }
+
+    #[test]
+    fn set_and_array_test() {
+        let cmd = command! {
+            name: "test",
+            desc: "test desc",
+            args: [
+                ["vector", "an embedding", ArgType::Arg, f64, Collection::Array(4), None],
+                ["members", "some members", ArgType::Arg, String, Collection::Set, None],
+            ],
+        };
+
+        let src = cmd.to_client_builder(Output::Args);
+        assert!(src.contains("    vector: [f64; 4],\n    members: Vec<String>,\n"));
+        assert!(src.contains(
+            "        for v in IntoIterator::into_iter(self.vector) {
+            args.push(v.to_string().into_bytes());
+        }
+        args.push(self.members.len().to_string().into_bytes());
+        for v in self.members {
+            args.push(v.into_bytes());
+        }
+"
+        ));
+    }
+
+    #[test]
+    fn client_builders_test() {
+        let foo = command! {
+            name: "hello.foo",
+            desc: "foo",
+            args: [],
+        };
+        let bar = command! {
+            name: "hello.bar",
+            desc: "bar",
+            args: [],
+        };
+
+        let src = client_builders(&[foo, bar], Output::Args);
+        assert!(src.starts_with("// Generated by redismodule_cmd"));
+        assert!(src.find("HelloBar").unwrap() < src.find("HelloFoo").unwrap());
+    }
+}
@@ -0,0 +1,447 @@
+//! Deserialization of parsed args into any `Deserialize` type, with the
+//! `serde` feature. Args are a map from their names to their values, with
+//! `Vec`, `Set` and `Array` args as sequences and `Map` args as maps, so
+//! existing structs can be filled straight from a command:
+//!
+//! ```text
+//! #[derive(Deserialize)]
+//! struct Foo {
+//!     input: String,
+//!     n: Option<u64>,
+//!     vec1: Vec<i64>,
+//! }
+//!
+//! let foo: Foo = cmd.parse_args_into(args)?;
+//! ```
+//!
+//! `Option` fields are `None` for optional args and kwargs that weren't
+//! given, even if they have a default. Durations deserialize into
+//! `std::time::Duration`, memory sizes into integers, coordinates into
+//! floats, JSON args like `serde_json::Value` does, and the other data types
+//! of redis commands into strings as they are typed, which also fills unit
+//! variants of enums.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use redis_module::RedisError;
+use serde::de::value::{MapDeserializer, SeqDeserializer, StringDeserializer};
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, Visitor};
+use serde::forward_to_deserialize_any;
+
+use crate::{
+    types, Command, Given, Latitude, Longitude, MemorySize, Milliseconds, Pairs, Seconds, Set,
+    Value,
+};
+
+/// Error deserializing parsed args, e.g. a missing field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+impl From<Error> for RedisError {
+    fn from(err: Error) -> Self {
+        RedisError::String(err.0)
+    }
+}
+
+impl Command {
+    /// Parses args like `parse_args`, deserializing them into `T`.
+    pub fn parse_args_into<T: DeserializeOwned>(
+        &self,
+        raw_args: Vec<String>,
+    ) -> Result<T, RedisError> {
+        let (args, given) = self.parse(raw_args)?;
+        let args = ArgsDeserializer {
+            args,
+            given: Some(given),
+        };
+        Ok(T::deserialize(args)?)
+    }
+}
+
+/// A `Deserializer` of args parsed by `parse_args`.
+pub struct ArgsDeserializer {
+    args: HashMap<&'static str, Box<dyn Value>>,
+    /// Names of the args that were given, or `None` if all were.
+    given: Option<Given>,
+}
+
+impl ArgsDeserializer {
+    /// Deserializes args parsed by `parse_args`. As defaults can't be told
+    /// apart from given args here, `Option` fields are only `None` for args
+    /// missing from `args`; use `Command::parse_args_into` to leave args
+    /// that weren't given out.
+    pub fn new(args: HashMap<&'static str, Box<dyn Value>>) -> Self {
+        ArgsDeserializer { args, given: None }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for ArgsDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_map(ArgsAccess {
+            args: self.args.into_iter(),
+            given: self.given,
+            value: None,
+        })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+struct ArgsAccess {
+    args: std::collections::hash_map::IntoIter<&'static str, Box<dyn Value>>,
+    given: Option<Given>,
+    value: Option<ValueDeserializer>,
+}
+
+impl<'de> MapAccess<'de> for ArgsAccess {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let (name, val) = match self.args.next() {
+            Some(arg) => arg,
+            None => return Ok(None),
+        };
+        let given = match &self.given {
+            Some(given) => given.contains(name),
+            None => true,
+        };
+        self.value = Some(ValueDeserializer { val, given });
+        seed.deserialize(IntoDeserializer::<Error>::into_deserializer(name))
+            .map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        match self.value.take() {
+            Some(val) => seed.deserialize(val),
+            None => Err(de::Error::custom("value is missing its arg name")),
+        }
+    }
+}
+
+/// A `Deserializer` of a parsed value, `given` unless it is a default.
+struct ValueDeserializer {
+    val: Box<dyn Value>,
+    given: bool,
+}
+
+impl ValueDeserializer {
+    /// The value as a string, for strings and data types without a serde
+    /// counterpart.
+    fn string(&self) -> Option<String> {
+        match (*self.val).as_any().downcast_ref::<String>() {
+            Some(s) => Some(s.clone()),
+            None => types::raw(&*self.val),
+        }
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for ValueDeserializer {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let any = (*self.val).as_any();
+
+        #[cfg(feature = "json")]
+        if let Some(v) = any.downcast_ref::<serde_json::Value>() {
+            return de::Deserializer::deserialize_any(v.clone(), visitor)
+                .map_err(de::Error::custom);
+        }
+
+        if let Some(n) = any.downcast_ref::<u64>() {
+            visitor.visit_u64(*n)
+        } else if let Some(n) = any.downcast_ref::<i64>() {
+            visitor.visit_i64(*n)
+        } else if let Some(n) = any.downcast_ref::<f64>() {
+            visitor.visit_f64(*n)
+        } else if let Some(vals) = any
+            .downcast_ref::<Vec<Box<dyn Value>>>()
+            .or_else(|| any.downcast_ref::<Set>().map(|s| &s.values))
+        {
+            let vals = vals.iter().map(|v| ValueDeserializer {
+                val: v.clone(),
+                given: true,
+            });
+            visitor.visit_seq(SeqDeserializer::new(vals))
+        } else if let Some(pairs) = any.downcast_ref::<Pairs>() {
+            let pairs = pairs.iter().map(|(k, v)| {
+                let key = ValueDeserializer {
+                    val: k.clone(),
+                    given: true,
+                };
+                let val = ValueDeserializer {
+                    val: v.clone(),
+                    given: true,
+                };
+                (key, val)
+            });
+            visitor.visit_map(MapDeserializer::new(pairs))
+        } else if let Some(d) = any
+            .downcast_ref::<Seconds>()
+            .map(|s| s.0)
+            .or_else(|| any.downcast_ref::<Milliseconds>().map(|m| m.0))
+        {
+            // like the serde impl of Duration
+            let parts = vec![d.as_secs(), u64::from(d.subsec_nanos())];
+            visitor.visit_seq(SeqDeserializer::new(parts.into_iter()))
+        } else if let Some(size) = any.downcast_ref::<MemorySize>() {
+            visitor.visit_u64(size.0)
+        } else if let Some(lon) = any.downcast_ref::<Longitude>() {
+            visitor.visit_f64(lon.0)
+        } else if let Some(lat) = any.downcast_ref::<Latitude>() {
+            visitor.visit_f64(lat.0)
+        } else if let Some(s) = self.string() {
+            visitor.visit_string(s)
+        } else {
+            Err(de::Error::custom(format!(
+                "{} can't be deserialized",
+                (*self.val).type_name()
+            )))
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.given {
+            visitor.visit_some(self)
+        } else {
+            visitor.visit_none()
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        #[cfg(feature = "json")]
+        if let Some(v) = (*self.val).as_any().downcast_ref::<serde_json::Value>() {
+            return de::Deserializer::deserialize_enum(v.clone(), name, variants, visitor)
+                .map_err(de::Error::custom);
+        }
+
+        match self.string() {
+            Some(s) => visitor.visit_enum(StringDeserializer::<Error>::new(s)),
+            None => self.deserialize_any(visitor).map_err(|_| {
+                de::Error::custom(format!(
+                    "expected a variant of {}, one of {}",
+                    name,
+                    variants.join(", ")
+                ))
+            }),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ArgsDeserializer;
+    use crate::{ArgType, Collection, Command, DistanceUnit, Seconds, StreamId};
+    use serde::Deserialize;
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    fn cmd() -> Command {
+        command! {
+            name: "hello.foo",
+            desc: "hello command example",
+            args: [
+                ["input", "a string", ArgType::Arg, String, Collection::Unit, None],
+                ["optional", "a string", ArgType::Arg, String, Collection::Unit, Some(Box::new("baz".to_owned()))],
+                ["n", "some number", ArgType::Kwarg, u64, Collection::Unit, Some(Box::new(1_u64))],
+                ["vec1", "a vector of ints", ArgType::Kwarg, i64, Collection::Vec, None],
+                ["ex", "a ttl", ArgType::Kwarg, Seconds, Collection::Unit, Some(Box::new(Seconds(Duration::from_secs(10))))],
+                ["unit", "a unit", ArgType::Kwarg, DistanceUnit, Collection::Unit, Some(Box::new(DistanceUnit::M))],
+                ["id", "a stream ID", ArgType::Kwarg, StreamId, Collection::Unit, Some(Box::new(StreamId::Max))],
+            ],
+        }
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Unit {
+        M,
+        Km,
+        Mi,
+        Ft,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Foo {
+        input: String,
+        optional: Option<String>,
+        n: u64,
+        vec1: Vec<i64>,
+        ex: Duration,
+        unit: Unit,
+        id: String,
+    }
+
+    fn args(a: &[&str]) -> Vec<String> {
+        a.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_args_into_test() {
+        let foo: Foo = cmd()
+            .parse_args_into(args(&[
+                "hello.foo",
+                "a",
+                "vec1",
+                "2",
+                "3",
+                "-4",
+                "unit",
+                "KM",
+            ]))
+            .unwrap();
+        assert_eq!(
+            foo,
+            Foo {
+                input: "a".to_owned(),
+                optional: None,
+                n: 1,
+                vec1: vec![3, -4],
+                ex: Duration::from_secs(10),
+                unit: Unit::Km,
+                id: "+".to_owned(),
+            }
+        );
+
+        let foo: Foo = cmd()
+            .parse_args_into(args(&["hello.foo", "a", "b", "vec1", "0", "ex", "500ms"]))
+            .unwrap();
+        assert_eq!(foo.optional, Some("b".to_owned()));
+        assert_eq!(foo.ex, Duration::from_millis(500));
+
+        // parse errors come first
+        assert!(cmd()
+            .parse_args_into::<Foo>(args(&["hello.foo", "a"]))
+            .is_err());
+
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Missing {
+            input: String,
+            other: u64,
+        }
+        let err = cmd()
+            .parse_args_into::<Missing>(args(&["hello.foo", "a", "vec1", "0"]))
+            .unwrap_err();
+        assert_eq!(format!("{:?}", err), r#"String("missing field `other`")"#);
+
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Mistyped {
+            input: u64,
+        }
+        assert!(cmd()
+            .parse_args_into::<Mistyped>(args(&["hello.foo", "a", "vec1", "0"]))
+            .is_err());
+    }
+
+    #[test]
+    fn map_test() {
+        let cmd = command! {
+            name: "test",
+            desc: "test desc",
+            args: [
+                ["attrs", "some attributes", ArgType::Arg, u64, Collection::Map(String), None],
+            ],
+        };
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Attrs {
+            attrs: HashMap<String, u64>,
+        }
+        let attrs: Attrs = cmd
+            .parse_args_into(args(&["test", "2", "a", "1", "b", "2"]))
+            .unwrap();
+        assert_eq!(attrs.attrs["a"], 1);
+        assert_eq!(attrs.attrs["b"], 2);
+    }
+
+    #[test]
+    fn set_and_array_test() {
+        let cmd = command! {
+            name: "test",
+            desc: "test desc",
+            args: [
+                ["members", "some members", ArgType::Arg, String, Collection::Set, None],
+                ["vector", "an embedding", ArgType::Arg, f64, Collection::Array(2), None],
+            ],
+        };
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Embedding {
+            members: Vec<String>,
+            vector: [f64; 2],
+        }
+        let embedding: Embedding = cmd
+            .parse_args_into(args(&["test", "3", "a", "b", "a", "1", "-1"]))
+            .unwrap();
+        assert_eq!(
+            embedding,
+            Embedding {
+                members: vec!["a".to_owned(), "b".to_owned()],
+                vector: [1.0, -1.0],
+            }
+        );
+    }
+
+    #[test]
+    fn args_deserializer_test() {
+        let parsed = cmd()
+            .parse_args(args(&["hello.foo", "a", "vec1", "1", "7"]))
+            .unwrap();
+        let foo = Foo::deserialize(ArgsDeserializer::new(parsed)).unwrap();
+        // defaults can't be told apart from given args
+        assert_eq!(foo.optional, Some("baz".to_owned()));
+        assert_eq!(foo.vec1, vec![7]);
+    }
+}
@@ -0,0 +1,445 @@
+//! Generation of the command reference at runtime, without the need for
+//! nightly rust. Meant to be called from a small binary or a `build.rs`
+//! that has access to all commands of a module.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process;
+
+use itertools::Itertools;
+
+use crate::{types, Arg, ArgType, Collection, Command, Duplicates, Expect, Set};
+
+impl Command {
+    /// Renders the command reference section of this command as markdown.
+    pub fn to_markdown(&self) -> String {
+        let mut args = String::new();
+        for (i, arg) in self.doc_args().enumerate() {
+            if i == 0 {
+                args.push_str(TABLE_HEADER);
+            }
+            args.push_str(&arg.to_markdown());
+        }
+
+        let examples: Vec<&str> = self
+            .examples
+            .iter()
+            .filter(|e| e.expect == Expect::Parses)
+            .map(|e| e.invocation)
+            .collect();
+        let examples = if examples.is_empty() {
+            String::new()
+        } else {
+            format!("#### Example\n```\n{}\n```\n", examples.join("\n"))
+        };
+
+        format!(
+            "
+### {name}
+#### Format
+```
+{usage}
+```
+#### Description
+{desc}
+{examples}#### Parameters
+{args}
+",
+            name = self.name.to_uppercase(),
+            usage = self,
+            desc = self.desc,
+            examples = examples,
+            args = args
+        )
+    }
+
+    /// Args in the order they are documented: required args, optional args
+    /// and kwargs in alphabetical order of their keywords.
+    fn doc_args(&self) -> impl Iterator<Item = &Arg> {
+        self.required_args
+            .iter()
+            .chain(self.optional_args.iter())
+            .chain(self.keyword_groups().flat_map(|(_, args)| args))
+    }
+}
+
+const TABLE_HEADER: &str = "
+| Name | Keyword | Type | Kind | Collection | Required | Default | Description |
+| --- | --- | --- | --- | --- | --- | --- | --- |
+";
+
+impl Arg {
+    /// Renders the row of this arg in the parameters table.
+    fn to_markdown(&self) -> String {
+        let (kind, keyword) = match self.arg_type {
+            ArgType::Arg => ("Positional", String::new()),
+            ArgType::Kwarg => ("Keyword", self.token.to_uppercase()),
+        };
+        let mut collection = match (&self.kind, self.max_len) {
+            (Collection::Unit, _) => "Single value".to_owned(),
+            (Collection::Vec, Some(max_len)) => {
+                format!("Vec: a count, then at most {} values", max_len)
+            }
+            (Collection::Vec, None) => "Vec: a count, then that many values".to_owned(),
+            (Collection::Set, Some(max_len)) => {
+                format!("Set: a count, then at most {} values", max_len)
+            }
+            (Collection::Set, None) => "Set: a count, then that many values".to_owned(),
+            (Collection::Array(len), _) => format!("Array: exactly {} values", len),
+            (Collection::Map(key_type), max_len) => format!(
+                "Map: a count, then {} pairs of a `{}` key and a value",
+                max_len.map_or("that many".to_owned(), |m| format!("at most {}", m)),
+                short_type_name(key_type)
+            ),
+        };
+        if self.duplicates == Duplicates::Reject {
+            match self.kind {
+                Collection::Set => collection.push_str(", without duplicate values"),
+                _ => collection.push_str(", without duplicate keys"),
+            }
+        }
+        let (required, default) = match &self.default {
+            // data types of redis commands are shown as they are typed
+            Some(d) => match (types::raw(&**d), (**d).as_any().downcast_ref::<Set>()) {
+                (Some(raw), _) => ("Optional", format!("`{}`", raw)),
+                (None, Some(set)) => ("Optional", format!("`{:?}`", set.values)),
+                (None, None) => ("Optional", format!("`{:?}`", d)),
+            },
+            None => ("Required", String::new()),
+        };
+
+        format!(
+            "| {name} | {keyword} | `{type_name}` | {kind} | {collection} | {required} | {default} | {desc} |\n",
+            name = self.arg,
+            keyword = keyword,
+            type_name = self.type_label(),
+            kind = kind,
+            collection = collection,
+            required = required,
+            default = escape(&default),
+            desc = escape(self.desc)
+        )
+    }
+}
+
+impl Arg {
+    /// Name of the data type, as shown in the parameters table.
+    #[cfg(feature = "json")]
+    fn type_label(&self) -> String {
+        if self.type_name != std::any::type_name::<serde_json::Value>() {
+            return short_type_name(self.type_name);
+        }
+        match &self.schema {
+            Some(schema) => format!("JSON<{}>", short_type_name(schema.type_name)),
+            None => "JSON".to_owned(),
+        }
+    }
+
+    #[cfg(not(feature = "json"))]
+    fn type_label(&self) -> String {
+        short_type_name(self.type_name)
+    }
+}
+
+/// Strips the module paths from a type name, including those of generic
+/// arguments, e.g. `Vec<Config>` for `alloc::vec::Vec<my_module::Config>`.
+fn short_type_name(type_name: &str) -> String {
+    let mut short = String::new();
+    let mut path = String::new();
+    for c in type_name.chars().chain(std::iter::once(' ')) {
+        if c.is_alphanumeric() || c == '_' || c == ':' {
+            path.push(c);
+            continue;
+        }
+        short.push_str(path.rsplit("::").next().unwrap_or(""));
+        path.clear();
+        short.push(c);
+    }
+    short.pop();
+
+    short
+}
+
+/// Escapes pipes, which would otherwise end a table cell.
+fn escape(s: &str) -> String {
+    s.replace('|', "\\|")
+}
+
+/// Renders the command reference of all commands, sorted by command name.
+pub fn reference(cmds: &[Command]) -> String {
+    let mut output = String::new();
+    for cmd in cmds.iter().sorted_by_key(|c| c.name) {
+        output.push_str(&cmd.to_markdown());
+        output.push('\n');
+    }
+
+    output
+}
+
+/// Writes the command reference of all commands to `path`, creating parent
+/// directories as needed. The file is replaced atomically, and left untouched
+/// if its contents wouldn't change, so that repeated builds are no-ops.
+pub fn write_reference<P: AsRef<Path>>(path: P, cmds: &[Command]) -> io::Result<()> {
+    let path = path.as_ref();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    write_if_changed(path, &reference(cmds))
+}
+
+/// Removes the sections the `rediscmd_doc` attribute wrote to `dir`, e.g.
+/// `doc/commands`, of commands that aren't in `cmds`, like renamed or removed
+/// ones. Macro expansion never sees all commands of a module, so it never
+/// removes sections itself: call this with all commands instead, e.g. next to
+/// `write_reference`. Files that aren't `.md` are left alone, as is a missing
+/// `dir`.
+pub fn remove_stale_sections<P: AsRef<Path>>(dir: P, cmds: &[Command]) -> io::Result<()> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    let current: Vec<String> = cmds.iter().map(|c| section_file(c.name)).collect();
+    for entry in entries {
+        let path = entry?.path();
+        let stale = match path.file_name().and_then(|f| f.to_str()) {
+            Some(f) => f.ends_with(".md") && !current.iter().any(|c| c == f),
+            None => false,
+        };
+        if stale {
+            fs::remove_file(&path)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Name of the file `rediscmd_doc` writes the section of a command to.
+fn section_file(name: &str) -> String {
+    let filename: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | '0'..='9' | '.' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect();
+    format!("{}.md", filename)
+}
+
+/// Atomically replaces the file at `path` with `output`, unless it already
+/// has these contents.
+pub(crate) fn write_if_changed(path: &Path, output: &str) -> io::Result<()> {
+    if let Ok(current) = fs::read_to_string(path) {
+        if current == output {
+            return Ok(());
+        }
+    }
+
+    let tmp = path.with_extension(format!("{}.tmp", process::id()));
+    fs::write(&tmp, output)?;
+    fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{reference, remove_stale_sections, short_type_name, write_reference};
+    use crate::{ArgType, Collection, Duplicates, Pairs, Set, StreamId};
+    use std::fs;
+
+    #[test]
+    fn to_markdown_test() {
+        let cmd = command! {
+            name: "hello.foo",
+            desc: "hello command example",
+            args: [
+                ["input", "a string", ArgType::Arg, String, Collection::Unit, None],
+                ["n", "some number", ArgType::Kwarg, u64, Collection::Unit, Some(Box::new(1_u64))],
+                ["vec1", "a vector of ints | bits", ArgType::Kwarg, i64, Collection::Vec, None, max_len: 100],
+            ],
+            examples: [
+                "hello.foo bar vec1 2 1 2",
+                "hello.foo bar n 3 vec1 0",
+                ["hello.foo bar", Err],
+            ],
+        };
+
+        let exp = "
+### HELLO.FOO
+#### Format
+```
+HELLO.FOO input [N n] VEC1 count vec1 [vec1 ...]
+```
+#### Description
+hello command example
+#### Example
+```
+hello.foo bar vec1 2 1 2
+hello.foo bar n 3 vec1 0
+```
+#### Parameters
+
+| Name | Keyword | Type | Kind | Collection | Required | Default | Description |
+| --- | --- | --- | --- | --- | --- | --- | --- |
+| input |  | `String` | Positional | Single value | Required |  | a string |
+| n | N | `u64` | Keyword | Single value | Optional | `1` | some number |
+| vec1 | VEC1 | `i64` | Keyword | Vec: a count, then at most 100 values | Required |  | a vector of ints \\| bits |
+
+";
+        assert_eq!(cmd.to_markdown(), exp);
+    }
+
+    #[test]
+    fn short_type_name_test() {
+        assert_eq!(short_type_name("alloc::string::String"), "String");
+        assert_eq!(short_type_name("u64"), "u64");
+        assert_eq!(
+            short_type_name("alloc::vec::Vec<my_module::Config>"),
+            "Vec<Config>"
+        );
+        assert_eq!(
+            short_type_name(
+                "std::collections::hash::map::HashMap<alloc::string::String, (a::B, [c::D; 2])>"
+            ),
+            "HashMap<String, (B, [D; 2])>"
+        );
+    }
+
+    #[test]
+    fn stream_id_test() {
+        let cmd = command! {
+            name: "xrange",
+            desc: "range of a stream",
+            args: [
+                ["start", "start of the range", ArgType::Arg, StreamId, Collection::Unit, None],
+                ["end", "end of the range", ArgType::Arg, StreamId, Collection::Unit, Some(Box::new(StreamId::Max))],
+            ],
+        };
+
+        let md = cmd.to_markdown();
+        assert!(md.contains(
+            "| start |  | `StreamId` | Positional | Single value | Required |  | start of the range |\n"
+        ));
+        assert!(md.contains(
+            "| end |  | `StreamId` | Positional | Single value | Optional | `+` | end of the range |\n"
+        ));
+    }
+
+    #[test]
+    fn map_test() {
+        let cmd = command! {
+            name: "test",
+            desc: "test desc",
+            args: [
+                ["attrs", "some attributes", ArgType::Arg, u64, Collection::Map(String), None, duplicates: Duplicates::Reject],
+                ["tags", "some tags", ArgType::Kwarg, String, Collection::Map(u64), Some(Box::new(Pairs::new())), max_len: 10],
+            ],
+        };
+
+        let md = cmd.to_markdown();
+        assert!(md.contains(
+            "TEST count field attrs [field attrs ...] [TAGS count field tags [field tags ...]]"
+        ));
+        assert!(md.contains(
+            "| attrs |  | `u64` | Positional | Map: a count, then that many pairs of a `String` key and a value, without duplicate keys | Required |  | some attributes |\n"
+        ));
+        assert!(md.contains(
+            "| tags | TAGS | `String` | Keyword | Map: a count, then at most 10 pairs of a `u64` key and a value | Optional | `[]` | some tags |\n"
+        ));
+    }
+
+    #[test]
+    fn set_and_array_test() {
+        let cmd = command! {
+            name: "test",
+            desc: "test desc",
+            args: [
+                ["vector", "an embedding", ArgType::Arg, f64, Collection::Array(128), None],
+                ["members", "some members", ArgType::Kwarg, String, Collection::Set, Some(Box::new(Set::default())), duplicates: Duplicates::Reject],
+            ],
+        };
+
+        let md = cmd.to_markdown();
+        assert!(md.contains("TEST vector_1 ... vector_128 [MEMBERS count members [members ...]]"));
+        assert!(md.contains(
+            "| vector |  | `f64` | Positional | Array: exactly 128 values | Required |  | an embedding |\n"
+        ));
+        assert!(md.contains(
+            "| members | MEMBERS | `String` | Keyword | Set: a count, then that many values, without duplicate values | Optional | `[]` | some members |\n"
+        ));
+    }
+
+    #[test]
+    fn reference_test() {
+        let foo = command! {
+            name: "hello.foo",
+            desc: "foo",
+            args: [],
+        };
+        let bar = command! {
+            name: "hello.bar",
+            desc: "bar",
+            args: [],
+        };
+
+        let output = reference(&[foo, bar]);
+        assert!(output.find("HELLO.BAR").unwrap() < output.find("HELLO.FOO").unwrap());
+    }
+
+    #[test]
+    fn write_reference_test() {
+        let cmds = vec![command! {
+            name: "hello.foo",
+            desc: "foo",
+            args: [],
+        }];
+
+        let dir = std::env::temp_dir().join(format!("rediscmd-{}", std::process::id()));
+        let path = dir.join("doc").join("COMMAND_REFERENCE_GEN.md");
+        write_reference(&path, &cmds).unwrap();
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+
+        // unchanged output leaves the file alone
+        write_reference(&path, &cmds).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().modified().unwrap(), modified);
+        assert_eq!(fs::read_to_string(&path).unwrap(), reference(&cmds));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn remove_stale_sections_test() {
+        let cmds = vec![
+            command! {
+                name: "hello.foo",
+                desc: "foo",
+                args: [],
+            },
+            command! {
+                name: "Hello Bar",
+                desc: "bar",
+                args: [],
+            },
+        ];
+
+        let dir = std::env::temp_dir().join(format!("rediscmd-sections-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for file in &["hello.foo.md", "hello_bar.md", "hello.baz.md", "notes.txt"] {
+            fs::write(dir.join(file), "").unwrap();
+        }
+
+        remove_stale_sections(&dir, &cmds).unwrap();
+        let mut files: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        assert_eq!(files, vec!["hello.foo.md", "hello_bar.md", "notes.txt"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(remove_stale_sections(&dir, &cmds).is_ok());
+    }
+}
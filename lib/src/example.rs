@@ -0,0 +1,127 @@
+use redis_module::RedisError;
+
+use crate::Command;
+
+/// A sample invocation of a command, shown in the command reference and
+/// checked against the parser by `Command::check_examples`.
+#[derive(Debug, PartialEq)]
+pub struct Example {
+    pub invocation: &'static str,
+    pub expect: Expect,
+}
+
+/// Expected outcome of parsing an example.
+#[derive(Debug, PartialEq)]
+pub enum Expect {
+    Parses,
+    Fails,
+    FailsWith(&'static str),
+}
+
+impl Example {
+    pub fn new(invocation: &'static str, expect: Expect) -> Self {
+        Example { invocation, expect }
+    }
+
+    /// Splits the invocation into raw args on whitespace. Double quotes
+    /// group args containing whitespace, e.g. `hello.foo "a b"`.
+    pub fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        let mut current: Option<String> = None;
+        let mut quoted = false;
+
+        for c in self.invocation.chars() {
+            match c {
+                '"' => {
+                    quoted = !quoted;
+                    current.get_or_insert_with(String::new);
+                }
+                c if c.is_whitespace() && !quoted => {
+                    if let Some(arg) = current.take() {
+                        args.push(arg);
+                    }
+                }
+                c => current.get_or_insert_with(String::new).push(c),
+            }
+        }
+        if let Some(arg) = current {
+            args.push(arg);
+        }
+
+        args
+    }
+}
+
+impl Command {
+    pub fn add_example(&mut self, example: Example) {
+        self.examples.push(example);
+    }
+
+    /// Runs every example through `parse_args` and checks the outcome, so
+    /// that the command reference never shows an invocation the parser
+    /// rejects. Meant to be called from tests.
+    pub fn check_examples(&self) -> Result<(), RedisError> {
+        for example in self.examples.iter() {
+            let res = self.parse_args(example.args());
+            let ok = match (&example.expect, &res) {
+                (Expect::Parses, Ok(_)) => true,
+                (Expect::Fails, Err(_)) => true,
+                (Expect::FailsWith(msg), Err(e)) => e.to_string() == *msg,
+                _ => false,
+            };
+
+            if !ok {
+                return Err(RedisError::String(format!(
+                    "Example `{}` of {} expected to be {:?}, got {:?}",
+                    example.invocation, self.name, example.expect, res
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Example, Expect};
+    use crate::{ArgType, Collection};
+
+    #[test]
+    fn args_test() {
+        let example = Example::new(r#"hello.foo  bar "a b" """#, Expect::Parses);
+        assert_eq!(example.args(), vec!["hello.foo", "bar", "a b", ""]);
+    }
+
+    #[test]
+    fn check_examples_test() {
+        let cmd = command! {
+            name: "hello.foo",
+            desc: "foo",
+            args: [
+                ["input", "a string", ArgType::Arg, String, Collection::Unit, None],
+                ["n", "some number", ArgType::Kwarg, u64, Collection::Unit, Some(Box::new(1_u64))],
+            ],
+            examples: [
+                "hello.foo bar",
+                ["hello.foo bar n 2", Ok],
+                ["hello.foo", Err],
+                ["hello.foo bar baz", Err("Unexpected arg baz")],
+            ],
+        };
+        assert_eq!(cmd.examples.len(), 4);
+        assert!(cmd.check_examples().is_ok());
+
+        let cmd = command! {
+            name: "hello.foo",
+            desc: "foo",
+            args: [
+                ["input", "a string", ArgType::Arg, String, Collection::Unit, None],
+            ],
+            examples: [
+                "hello.foo bar baz",
+            ],
+        };
+        assert!(cmd.check_examples().is_err());
+    }
+}
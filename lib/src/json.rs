@@ -0,0 +1,221 @@
+//! JSON documents as args, with the `json` feature. An arg of data type
+//! `serde_json::Value` takes a JSON document as a single raw arg, and can be
+//! checked against a type with the `schema` option, so that malformed
+//! documents fail in `parse_args` rather than in the command handler.
+
+use std::any::type_name;
+use std::fmt;
+
+#[cfg(feature = "proptest")]
+use proptest::prelude::*;
+use redis_module::RedisError;
+use serde::de::DeserializeOwned;
+#[cfg(feature = "proptest")]
+use serde::Serialize;
+
+use crate::types::RawArg;
+use crate::Value;
+
+/// A type that JSON args must deserialize into, set with `Arg::schema`:
+///
+/// ```text
+/// ["config", "a config", ArgType::Arg, serde_json::Value, Collection::Unit, None, schema: Schema::of::<Config>()]
+/// ```
+#[derive(Clone, Copy)]
+pub struct Schema {
+    /// Full name of the type, which tells schemas apart. The command
+    /// reference shows it without module paths.
+    pub type_name: &'static str,
+    check: fn(&str) -> Result<(), serde_json::Error>,
+    /// Generates raw documents of the type, see `Schema::arbitrary`.
+    #[cfg(feature = "proptest")]
+    strategy: Option<fn() -> BoxedStrategy<String>>,
+}
+
+impl Schema {
+    /// The schema of JSON args deserializing into `T`.
+    pub fn of<T: DeserializeOwned>() -> Self {
+        Schema {
+            type_name: type_name::<T>(),
+            check: |raw| serde_json::from_str::<T>(raw).map(|_| ()),
+            #[cfg(feature = "proptest")]
+            strategy: None,
+        }
+    }
+
+    /// The schema of JSON args deserializing into `T`, like `Schema::of`,
+    /// whose documents `Command::valid_args` generates from the `Arbitrary`
+    /// values of `T`. Values that don't serialize into a document
+    /// deserializing back into `T`, e.g. with a NaN float, are skipped.
+    #[cfg(feature = "proptest")]
+    pub fn arbitrary<T: DeserializeOwned + Serialize + Arbitrary + 'static>() -> Self {
+        Schema {
+            strategy: Some(|| {
+                any::<T>()
+                    .prop_filter_map("not a document of the type", |v| {
+                        serde_json::to_string(&v)
+                            .ok()
+                            .filter(|raw| serde_json::from_str::<T>(raw).is_ok())
+                    })
+                    .boxed()
+            }),
+            ..Schema::of::<T>()
+        }
+    }
+
+    /// Checks that a raw arg deserializes into the type.
+    pub(crate) fn check(&self, raw: &str) -> Result<(), RedisError> {
+        (self.check)(raw).map_err(invalid)
+    }
+
+    /// Generates raw documents of the type, if made with
+    /// `Schema::arbitrary`.
+    #[cfg(feature = "proptest")]
+    pub(crate) fn strategy(&self) -> Option<BoxedStrategy<String>> {
+        self.strategy.map(|s| s())
+    }
+}
+
+impl fmt::Debug for Schema {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Schema({})", self.type_name)
+    }
+}
+
+impl PartialEq for Schema {
+    fn eq(&self, other: &Self) -> bool {
+        self.type_name == other.type_name
+    }
+}
+
+impl RawArg for serde_json::Value {
+    fn parse(_cmd: &str, raw: &str) -> Result<Self, RedisError> {
+        serde_json::from_str(raw).map_err(invalid)
+    }
+
+    fn raw(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// The error of a malformed document, with the position given by serde,
+/// e.g. `invalid JSON, expected value at line 1 column 9`.
+fn invalid(err: serde_json::Error) -> RedisError {
+    RedisError::String(format!("invalid JSON, {}", err))
+}
+
+impl dyn Value {
+    /// Deserializes a parsed JSON arg into `T`, usually the type of its
+    /// schema.
+    pub fn deserialize_json<T: DeserializeOwned>(self: Box<Self>) -> Result<T, RedisError> {
+        serde_json::from_value(self.as_json()?).map_err(invalid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Schema;
+    use crate::types::RawArg;
+    use crate::{ArgType, Collection};
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Config {
+        name: String,
+        size: u64,
+    }
+
+    #[test]
+    fn parse_test() {
+        let val = serde_json::Value::parse("json.set", r#"{"a": [1, 2]}"#).unwrap();
+        assert_eq!(val, serde_json::json!({"a": [1, 2]}));
+        assert_eq!(val.raw(), r#"{"a":[1,2]}"#);
+
+        let err = serde_json::Value::parse("json.set", r#"{"a": [1, 2}"#).unwrap_err();
+        assert_eq!(
+            format!("{:?}", err),
+            r#"String("invalid JSON, expected `,` or `]` at line 1 column 12")"#
+        );
+    }
+
+    mod other {
+        #[derive(serde::Deserialize)]
+        pub struct Config {}
+    }
+
+    #[test]
+    fn schema_eq_test() {
+        assert_eq!(Schema::of::<Config>(), Schema::of::<Config>());
+        assert_ne!(Schema::of::<Config>(), Schema::of::<other::Config>());
+        assert_ne!(
+            Schema::of::<Vec<Config>>(),
+            Schema::of::<Vec<other::Config>>()
+        );
+    }
+
+    #[test]
+    fn schema_test() {
+        let cmd = command! {
+            name: "test",
+            desc: "test desc",
+            args: [
+                ["config", "a config", ArgType::Arg, serde_json::Value, Collection::Unit, None, schema: Schema::of::<Config>()],
+                ["meta", "metadata", ArgType::Kwarg, serde_json::Value, Collection::Unit, Some(Box::new(serde_json::Value::Null))],
+            ],
+        };
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+
+        let mut parsed = cmd
+            .parse_args(args(&[
+                "test",
+                r#"{"name": "x", "size": 3}"#,
+                "meta",
+                "[true]",
+            ]))
+            .unwrap();
+        assert_eq!(
+            parsed
+                .remove("config")
+                .unwrap()
+                .deserialize_json::<Config>()
+                .unwrap(),
+            Config {
+                name: "x".to_owned(),
+                size: 3
+            }
+        );
+        assert_eq!(
+            parsed.remove("meta").unwrap().as_json().unwrap(),
+            serde_json::json!([true])
+        );
+
+        let err = cmd
+            .parse_args(args(&["test", r#"{"name": "x"}"#]))
+            .unwrap_err();
+        assert_eq!(
+            format!("{:?}", err),
+            r#"String("invalid JSON, missing field `size` at line 1 column 13")"#
+        );
+        assert!(cmd.parse_args(args(&["test", "{"])).is_err());
+    }
+
+    #[test]
+    fn to_markdown_test() {
+        let cmd = command! {
+            name: "test",
+            desc: "test desc",
+            args: [
+                ["config", "a config", ArgType::Arg, serde_json::Value, Collection::Unit, None, schema: Schema::of::<Config>()],
+                ["meta", "metadata", ArgType::Kwarg, serde_json::Value, Collection::Unit, Some(Box::new(serde_json::json!({"a": 1})))],
+                ["configs", "some configs", ArgType::Kwarg, serde_json::Value, Collection::Unit, None, schema: Schema::of::<Vec<Config>>()],
+            ],
+        };
+
+        let md = cmd.to_markdown();
+        assert!(md.contains("| config |  | `JSON<Config>` | Positional |"));
+        assert!(md.contains("| configs | CONFIGS | `JSON<Vec<Config>>` | Keyword |"));
+        assert!(md.contains(
+            "| meta | META | `JSON` | Keyword | Single value | Optional | `{\"a\":1}` |"
+        ));
+    }
+}
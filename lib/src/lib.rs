@@ -4,10 +4,16 @@ extern crate redismodule_cmd_procmacros;
 
 #[cfg(feature = "docgen")]
 pub use redismodule_cmd_procmacros::rediscmd_doc;
+#[doc(hidden)]
+pub use redismodule_cmd_procmacros::validate_command;
 
 use std::any::{type_name, Any};
-use std::collections::HashMap;
-use std::fmt::Debug;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fmt::{self, Debug};
+use std::hash::Hash;
+use std::time::Duration;
 
 use dyn_clonable::*;
 use itertools::Itertools;
@@ -16,6 +22,23 @@ use redis_module::{parse_float, parse_integer, parse_unsigned_integer, RedisErro
 #[macro_use]
 mod macros;
 
+pub mod codegen;
+#[cfg(feature = "serde")]
+pub mod de;
+pub mod docgen;
+mod example;
+#[cfg(feature = "json")]
+pub mod json;
+#[cfg(feature = "proptest")]
+pub mod strategy;
+pub mod types;
+
+pub use example::{Example, Expect};
+pub use types::{
+    DistanceUnit, Latitude, LexBound, Longitude, MemorySize, Milliseconds, ScoreBound, Seconds,
+    StreamId,
+};
+
 thread_local! {
     static TN_STRING: &'static str = type_name::<String>();
     static TN_U64: &'static str = type_name::<u64>();
@@ -27,27 +50,13 @@ macro_rules! parse_arg {
     (
         $arg:ident,
         $next_arg:ident,
-        $raw_args:ident
+        $raw_args:ident,
+        $cmd:expr
     ) => {
         match $arg.kind {
-            Collection::Unit => match $arg.type_name {
-                n if n == TN_STRING.with(|t| t.clone()) => Box::new($next_arg.clone()),
-                n if n == TN_U64.with(|t| t.clone()) => {
-                    Box::new(parse_unsigned_integer($next_arg.as_str())?)
-                }
-                n if n == TN_I64.with(|t| t.clone()) => {
-                    Box::new(parse_integer($next_arg.as_str())?)
-                }
-                n if n == TN_F64.with(|t| t.clone()) => Box::new(parse_float($next_arg.as_str())?),
-                _ => {
-                    return Err(RedisError::String(format!(
-                        "{} is not a supported type",
-                        $arg.type_name
-                    )))
-                }
-            },
+            Collection::Unit => $cmd.parse_value($arg, &$next_arg)?,
             Collection::Vec => {
-                let len = parse_unsigned_integer($next_arg.as_str())? as usize;
+                let len = $cmd.parse_len($arg, &$next_arg, $raw_args.len())?;
                 let mut val: Vec<Box<dyn Value>> = Vec::with_capacity(len);
                 for _ in 0..len {
                     match $raw_args.next() {
@@ -58,33 +67,115 @@ macro_rules! parse_arg {
                             return Err(RedisError::WrongArity);
                         }
                     };
-                    match $arg.type_name {
-                        n if n == TN_STRING.with(|t| t.clone()) => {
-                            val.push(Box::new($next_arg.clone()));
+                    val.push($cmd.parse_value($arg, &$next_arg)?);
+                }
+                Box::new(val)
+            }
+            Collection::Set => {
+                let len = $cmd.parse_len($arg, &$next_arg, $raw_args.len())?;
+                let mut set = Set {
+                    values: Vec::with_capacity(len),
+                    duplicates: false,
+                };
+                // values by their raw form, like the keys of a `Map`
+                let mut seen: HashSet<String> = HashSet::with_capacity(len);
+                for _ in 0..len {
+                    match $raw_args.next() {
+                        Some(next) => {
+                            $next_arg = next;
+                        }
+                        None => {
+                            return Err(RedisError::WrongArity);
                         }
-                        n if n == TN_U64.with(|t| t.clone()) => {
-                            val.push(Box::new(parse_unsigned_integer($next_arg.as_str())?));
+                    };
+                    let val = $cmd.parse_value($arg, &$next_arg)?;
+                    if seen.insert(raw_arg(&*val)) {
+                        set.values.push(val);
+                    } else if $arg.duplicates == Duplicates::Reject {
+                        return Err(RedisError::String(format!(
+                            "{} has a duplicate value {}",
+                            $arg.arg, $next_arg
+                        )));
+                    } else {
+                        set.duplicates = true;
+                    }
+                }
+                Box::new(set)
+            }
+            Collection::Array(len) => {
+                // the first value is the current arg
+                if len == 0 || len - 1 > $raw_args.len() {
+                    return Err(RedisError::WrongArity);
+                }
+                let mut val: Vec<Box<dyn Value>> = Vec::with_capacity(len);
+                val.push($cmd.parse_value($arg, &$next_arg)?);
+                for _ in 1..len {
+                    match $raw_args.next() {
+                        Some(next) => {
+                            $next_arg = next;
                         }
-                        n if n == TN_I64.with(|t| t.clone()) => {
-                            val.push(Box::new(parse_integer($next_arg.as_str())?));
+                        None => {
+                            return Err(RedisError::WrongArity);
                         }
-                        n if n == TN_F64.with(|t| t.clone()) => {
-                            val.push(Box::new(parse_float($next_arg.as_str())?));
+                    };
+                    val.push($cmd.parse_value($arg, &$next_arg)?);
+                }
+                Box::new(val)
+            }
+            Collection::Map(key_type) => {
+                let len = $cmd.parse_len($arg, &$next_arg, $raw_args.len() / 2)?;
+                let mut pairs: Pairs = Vec::with_capacity(len);
+                // positions of the keys by their raw form, so that e.g. `1`
+                // and `01` are the same u64
+                let mut keys: HashMap<String, usize> = HashMap::with_capacity(len);
+                for _ in 0..len {
+                    let (raw_key, raw_val) = match ($raw_args.next(), $raw_args.next()) {
+                        (Some(k), Some(v)) => (k, v),
+                        _ => return Err(RedisError::WrongArity),
+                    };
+                    let key = parse_raw(key_type, $cmd.name, &raw_key)?;
+                    let val = $cmd.parse_value($arg, &raw_val)?;
+                    match keys.entry(raw_arg(&*key)) {
+                        Entry::Occupied(e) => {
+                            if $arg.duplicates == Duplicates::Reject {
+                                return Err(RedisError::String(format!(
+                                    "{} has a duplicate key {}",
+                                    $arg.arg, raw_key
+                                )));
+                            }
+                            pairs[*e.get()].1 = val;
                         }
-                        _ => {
-                            return Err(RedisError::String(format!(
-                                "{} is not a supported type",
-                                $arg.type_name
-                            )))
+                        Entry::Vacant(e) => {
+                            e.insert(pairs.len());
+                            pairs.push((key, val));
                         }
                     }
                 }
-                Box::new(val)
+                Box::new(pairs)
             }
         }
     };
 }
 
+/// Parsed args by name, as returned by `parse_args`.
+pub(crate) type Args = HashMap<&'static str, Box<dyn Value>>;
+
+/// Names of the args that were given rather than filled with their default.
+pub(crate) type Given = HashSet<&'static str>;
+
+/// Parsed value of a `Map` arg: its keys with their values, in the order the
+/// keys were first given.
+pub type Pairs = Vec<(Box<dyn Value>, Box<dyn Value>)>;
+
+/// Parsed value of a `Set` arg, or its default, e.g. `Set::default()`.
+#[derive(Debug, Clone, Default)]
+pub struct Set {
+    /// The distinct values, in the order they were first given.
+    pub values: Vec<Box<dyn Value>>,
+    /// Whether any value was given more than once.
+    pub duplicates: bool,
+}
+
 #[derive(Debug, PartialEq)]
 pub struct Command {
     pub name: &'static str,
@@ -92,6 +183,27 @@ pub struct Command {
     pub required_args: Vec<Arg>,
     pub optional_args: Vec<Arg>,
     pub kwargs: HashMap<&'static str, Arg>,
+    /// Names of the kwargs following each keyword, in the order they were
+    /// added.
+    pub keywords: HashMap<&'static str, Vec<&'static str>>,
+    pub examples: Vec<Example>,
+    pub max_args: Option<usize>,
+    pub max_vec_len: Option<usize>,
+    pub kwarg_policy: KwargPolicy,
+}
+
+/// How keywords are told apart from the values of optional positional args.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KwargPolicy {
+    /// Any arg matching a keyword starts the kwargs, so optional positional
+    /// args can't have a value matching a keyword.
+    Greedy,
+    /// Keywords are only matched once all optional positional args are
+    /// given, so these can't be optional if there are required kwargs.
+    Positional,
+    /// Like `Positional`, but a `--` arg ends the optional positional args
+    /// early.
+    Terminator,
 }
 
 impl Command {
@@ -102,10 +214,119 @@ impl Command {
             required_args: Vec::new(),
             optional_args: Vec::new(),
             kwargs: HashMap::new(),
+            keywords: HashMap::new(),
+            examples: Vec::new(),
+            max_args: None,
+            max_vec_len: None,
+            kwarg_policy: KwargPolicy::Greedy,
+        }
+    }
+
+    /// Sets how keywords are told apart from the values of optional
+    /// positional args, `KwargPolicy::Greedy` by default, panicking with the
+    /// error of `try_kwarg_policy` if the args added before can't be given
+    /// with it.
+    pub fn kwarg_policy(self, kwarg_policy: KwargPolicy) -> Self {
+        let name = self.name;
+        match self.try_kwarg_policy(kwarg_policy) {
+            Ok(cmd) => cmd,
+            Err(e) => panic!("Invalid kwarg policy for {}: {}", name, e),
+        }
+    }
+
+    /// Sets the `KwargPolicy` like `kwarg_policy`, failing like `try_add_arg`
+    /// if the args added before can't be given with it.
+    pub fn try_kwarg_policy(mut self, kwarg_policy: KwargPolicy) -> Result<Self, RedisError> {
+        self.check_kwarg_policy(kwarg_policy, None)?;
+        self.kwarg_policy = kwarg_policy;
+        Ok(self)
+    }
+
+    /// Limits the number of raw args, counting the command name.
+    pub fn max_args(mut self, max_args: usize) -> Self {
+        self.max_args = Some(max_args);
+        self
+    }
+
+    /// Limits the number of values of `Vec` and `Set` args, or pairs of `Map`
+    /// args, without a limit of their own, see `Arg::max_len`.
+    pub fn max_vec_len(mut self, max_vec_len: usize) -> Self {
+        self.max_vec_len = Some(max_vec_len);
+        self
+    }
+
+    /// Checks that the args, along with `arg` if about to be added, can be
+    /// given with `kwarg_policy`: with `KwargPolicy::Positional`, keywords
+    /// only follow all optional positional args, so these can't be left out
+    /// before a required kwarg.
+    fn check_kwarg_policy(
+        &self,
+        kwarg_policy: KwargPolicy,
+        arg: Option<&Arg>,
+    ) -> Result<(), RedisError> {
+        if kwarg_policy != KwargPolicy::Positional {
+            return Ok(());
+        }
+
+        let optional = self
+            .optional_args
+            .iter()
+            .chain(arg.filter(|a| a.arg_type == ArgType::Arg && a.default.is_some()))
+            .map(|a| a.arg)
+            .next();
+        let required_kwarg = self
+            .kwargs
+            .values()
+            .chain(arg.filter(|a| a.arg_type == ArgType::Kwarg))
+            .filter(|a| a.default.is_none())
+            .map(|a| a.arg)
+            .min();
+        match (optional, required_kwarg) {
+            (Some(optional), Some(kwarg)) => Err(RedisError::String(format!(
+                "{} can't be optional with KwargPolicy::Positional, as it has to be given before the required kwarg {}",
+                optional, kwarg
+            ))),
+            _ => Ok(()),
         }
     }
 
+    /// Adds an arg, panicking with the error of `try_add_arg` if it is
+    /// invalid. Use `try_add_arg` where a panic would crash the server, e.g.
+    /// when building commands in a redis command handler.
     pub fn add_arg(&mut self, arg: Arg) {
+        if let Err(e) = self.try_add_arg(arg) {
+            panic!("Invalid arg for {}: {}", self.name, e);
+        }
+    }
+
+    /// Adds an arg, checking that its default has the declared data type,
+    /// which is otherwise only noticed when casting the parsed value, and
+    /// that it can be given along with the args added before.
+    pub fn try_add_arg(&mut self, arg: Arg) -> Result<(), RedisError> {
+        arg.check_default()?;
+
+        // names are the keys of the parse result
+        let duplicate = self.kwargs.contains_key(arg.arg)
+            || self
+                .required_args
+                .iter()
+                .chain(self.optional_args.iter())
+                .any(|a| a.arg == arg.arg);
+        if duplicate {
+            return Err(RedisError::String(format!(
+                "duplicate arg name {}",
+                arg.arg
+            )));
+        }
+        if arg.arg_type == ArgType::Kwarg && arg.token != arg.token.to_lowercase() {
+            return Err(RedisError::String(format!(
+                "keyword {} can never be matched, keywords are matched in lowercase",
+                arg.token
+            )));
+        }
+
+        self.check_kwarg_policy(self.kwarg_policy, Some(&arg))?;
+
         match arg.arg_type {
             ArgType::Arg => {
                 if arg.default.is_none() {
@@ -115,15 +336,86 @@ impl Command {
                 }
             }
             ArgType::Kwarg => {
+                // kwargs sharing a keyword are given together
+                let kwargs = &self.kwargs;
+                let names = self.keywords.entry(arg.token).or_default();
+                if let Some(first) = names.first().map(|n| &kwargs[n]) {
+                    if first.default.is_some() != arg.default.is_some() {
+                        return Err(RedisError::String(format!(
+                            "{} and {} share the keyword {}, so both need to be optional or required",
+                            first.arg, arg.arg, arg.token
+                        )));
+                    }
+                }
+                names.push(arg.arg);
                 self.kwargs.insert(arg.arg, arg);
             }
         }
+
+        Ok(())
+    }
+
+    /// Kwargs grouped by keyword, in alphabetical order of the keywords.
+    /// Kwargs sharing a keyword are in the order they were added.
+    pub fn keyword_groups(&self) -> impl Iterator<Item = (&'static str, Vec<&Arg>)> {
+        self.keywords
+            .iter()
+            .sorted_by_key(|(k, _)| *k)
+            .map(move |(k, names)| (*k, names.iter().map(|n| &self.kwargs[n]).collect()))
+    }
+
+    /// Computes the redis arity of the command, counting the command name.
+    /// A positive arity means an exact number of args, a negative arity
+    /// means at least that many.
+    pub fn arity(&self) -> i64 {
+        let mut min: i64 = 1;
+        let mut exact = self.optional_args.is_empty() && self.kwargs.is_empty();
+
+        for arg in self.required_args.iter() {
+            min += arg.min_args();
+            if let Collection::Vec | Collection::Set | Collection::Map(_) = arg.kind {
+                exact = false;
+            }
+        }
+
+        // required kwargs need the keyword as well as the values
+        let mut required_kwargs = false;
+        for (_, args) in self.keyword_groups() {
+            if args[0].default.is_none() {
+                min += 1 + args.iter().map(|a| a.min_args()).sum::<i64>();
+                required_kwargs = true;
+            }
+        }
+
+        // keywords only follow all optional args, or a terminator
+        if required_kwargs {
+            match self.kwarg_policy {
+                KwargPolicy::Greedy => (),
+                KwargPolicy::Positional => {
+                    min += self.optional_args.iter().map(|a| a.min_args()).sum::<i64>();
+                }
+                KwargPolicy::Terminator if !self.optional_args.is_empty() => min += 1,
+                KwargPolicy::Terminator => (),
+            }
+        }
+
+        if exact {
+            min
+        } else {
+            -min
+        }
     }
 
     pub fn parse_args(
         &self,
         raw_args: Vec<String>,
     ) -> Result<HashMap<&'static str, Box<dyn Value>>, RedisError> {
+        self.parse(raw_args).map(|(res, _)| res)
+    }
+
+    /// Parses args like `parse_args`, also returning the names of the args
+    /// that were given rather than filled with their default.
+    pub(crate) fn parse(&self, raw_args: Vec<String>) -> Result<(Args, Given), RedisError> {
         let mut raw_args = raw_args.into_iter();
         match raw_args.next() {
             Some(cmd_name) => {
@@ -137,6 +429,24 @@ impl Command {
             None => return Err(RedisError::WrongArity),
         }
 
+        // reject obviously wrong arg counts before parsing anything
+        let arity = self.arity();
+        let argc = raw_args.len() as i64 + 1;
+        if (arity > 0 && argc != arity) || (arity < 0 && argc < -arity) {
+            return Err(RedisError::String(format!(
+                "wrong number of arguments for '{}' command",
+                self.name
+            )));
+        }
+        if let Some(max_args) = self.max_args {
+            if argc > max_args as i64 {
+                return Err(RedisError::String(format!(
+                    "too many arguments for '{}' command, at most {} are allowed",
+                    self.name, max_args
+                )));
+            }
+        }
+
         let mut res = HashMap::new();
 
         // parse args
@@ -148,33 +458,49 @@ impl Command {
             if required_pos < self.required_args.len() {
                 let arg = &self.required_args[required_pos];
 
-                let val: Box<dyn Value> = parse_arg!(arg, next_arg, raw_args);
+                let val: Box<dyn Value> = parse_arg!(arg, next_arg, raw_args, self);
                 res.insert(arg.arg, val);
                 required_pos += 1;
 
                 continue;
             }
 
-            match self.kwargs.get(next_arg.to_lowercase().as_str()) {
-                Some(arg) => {
+            if do_optional && self.kwarg_policy == KwargPolicy::Terminator && next_arg == "--" {
+                do_optional = false;
+                continue;
+            }
+
+            // depending on the policy, keywords may not be matched while
+            // optional args are left
+            let optional_left = do_optional && optional_pos < self.optional_args.len();
+            let keyword = match self.kwarg_policy {
+                KwargPolicy::Positional | KwargPolicy::Terminator if optional_left => None,
+                _ => self.keywords.get(next_arg.to_lowercase().as_str()),
+            };
+
+            match keyword {
+                Some(names) => {
                     // if we can match named args, then done with optional
                     if do_optional {
                         do_optional = false;
                     }
 
-                    let val: Box<dyn Value> = match raw_args.next() {
-                        Some(mut next) => parse_arg!(arg, next, raw_args),
-                        None => return Err(RedisError::WrongArity),
-                    };
+                    for name in names.iter() {
+                        let arg = &self.kwargs[name];
+                        let val: Box<dyn Value> = match raw_args.next() {
+                            Some(mut next) => parse_arg!(arg, next, raw_args, self),
+                            None => return Err(RedisError::WrongArity),
+                        };
 
-                    res.insert(arg.arg, val);
+                        res.insert(arg.arg, val);
+                    }
                 }
                 None => {
                     // match optional args
-                    if do_optional && optional_pos < self.optional_args.len() {
+                    if optional_left {
                         let arg = &self.optional_args[optional_pos];
 
-                        let val: Box<dyn Value> = parse_arg!(arg, next_arg, raw_args);
+                        let val: Box<dyn Value> = parse_arg!(arg, next_arg, raw_args, self);
                         res.insert(arg.arg, val);
                         optional_pos += 1;
                     } else {
@@ -190,6 +516,7 @@ impl Command {
                 return Err(RedisError::String(format!("{} is required", v.arg)));
             }
         }
+        let given: Given = res.keys().copied().collect();
 
         // check if all optional args are fulfilled
         for v in self.optional_args.iter() {
@@ -208,18 +535,172 @@ impl Command {
             }
         }
 
-        Ok(res)
+        // check coordinates in the order they are given, so that pairs of
+        // them are reported together
+        let positional: Vec<&Arg> = self
+            .required_args
+            .iter()
+            .chain(self.optional_args.iter())
+            .collect();
+        types::check_coordinates(&positional, &res)?;
+        for (_, args) in self.keyword_groups() {
+            types::check_coordinates(&args, &res)?;
+        }
+
+        Ok((res, given))
+    }
+
+    /// Turns args parsed by `parse_args` back into raw args, e.g. to
+    /// replicate the effective command. The result is canonical: all
+    /// optional args are given, kwargs are in alphabetical order with
+    /// uppercase keywords, and parsing it gives back the same args.
+    pub fn to_argv(
+        &self,
+        parsed: &HashMap<&'static str, Box<dyn Value>>,
+    ) -> Result<Vec<String>, RedisError> {
+        let mut argv = vec![self.name.to_owned()];
+
+        for arg in self.required_args.iter() {
+            arg.push_raw_args(parsed, &mut argv)?;
+        }
+        for arg in self.optional_args.iter() {
+            let start = argv.len();
+            arg.push_raw_args(parsed, &mut argv)?;
+            // a value matching a keyword would end the optional args
+            if self.kwarg_policy == KwargPolicy::Greedy
+                && self
+                    .keywords
+                    .contains_key(argv[start].to_lowercase().as_str())
+            {
+                return Err(RedisError::String(format!(
+                    "{} of {} can't be given as a raw arg",
+                    argv[start], arg.arg
+                )));
+            }
+        }
+        for (keyword, args) in self.keyword_groups() {
+            argv.push(keyword.to_uppercase());
+            for arg in args {
+                arg.push_raw_args(parsed, &mut argv)?;
+            }
+        }
+
+        Ok(argv)
+    }
+
+    /// Parses a single raw value of an arg, i.e. the arg itself or one
+    /// of the values of a collection.
+    fn parse_value(&self, arg: &Arg, raw: &str) -> Result<Box<dyn Value>, RedisError> {
+        let val = parse_raw(arg.type_name, self.name, raw)?;
+        #[cfg(feature = "json")]
+        if let Some(schema) = &arg.schema {
+            schema.check(raw)?;
+        }
+        Ok(val)
+    }
+
+    /// Parses the count of a `Vec`, `Set` or `Map` arg. The count comes from the
+    /// client, so it is checked against the limits of the arg and the
+    /// number of items the `left` raw args can hold before anything is
+    /// allocated.
+    fn parse_len(&self, arg: &Arg, raw: &str, left: usize) -> Result<usize, RedisError> {
+        let len = parse_unsigned_integer(raw)?;
+        if let Some(max_len) = arg.max_len.or(self.max_vec_len) {
+            if len > max_len as u64 {
+                let items = match arg.kind {
+                    Collection::Map(_) => "pairs",
+                    _ => "values",
+                };
+                return Err(RedisError::String(format!(
+                    "{} can have at most {} {}",
+                    arg.arg, max_len, items
+                )));
+            }
+        }
+        if len > left as u64 {
+            return Err(RedisError::WrongArity);
+        }
+        Ok(len as usize)
+    }
+}
+
+/// Parses a single raw value of a data type, with the name of the command
+/// for errors that mention it.
+pub(crate) fn parse_raw(
+    type_name: &str,
+    cmd_name: &str,
+    raw: &str,
+) -> Result<Box<dyn Value>, RedisError> {
+    match type_name {
+        n if n == TN_STRING.with(|t| *t) => Ok(Box::new(raw.to_owned())),
+        n if n == TN_U64.with(|t| *t) => Ok(Box::new(parse_unsigned_integer(raw)?)),
+        n if n == TN_I64.with(|t| *t) => Ok(Box::new(parse_integer(raw)?)),
+        n if n == TN_F64.with(|t| *t) => Ok(Box::new(parse_float(raw)?)),
+        n => match types::parse(n, cmd_name, raw) {
+            Some(val) => val,
+            None => Err(RedisError::String(format!(
+                "{} is not a supported type",
+                type_name
+            ))),
+        },
+    }
+}
+
+/// Formats the usage line of the command, e.g.
+/// `HELLO.FOO input [optional] [N n] VEC1 count vec1 [vec1 ...]`.
+/// Kwargs are listed in alphabetical order of their keywords, after `[--]`
+/// with `KwargPolicy::Terminator`.
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name.to_uppercase())?;
+        for arg in self.required_args.iter() {
+            write!(f, " {}", arg.usage())?;
+        }
+        for arg in self.optional_args.iter() {
+            write!(f, " {}", arg.usage())?;
+        }
+        if self.kwarg_policy == KwargPolicy::Terminator
+            && !self.optional_args.is_empty()
+            && !self.kwargs.is_empty()
+        {
+            write!(f, " [--]")?;
+        }
+        for (keyword, args) in self.keyword_groups() {
+            let mut usage = keyword.to_uppercase();
+            for arg in args.iter() {
+                usage = format!("{} {}", usage, arg.value_usage());
+            }
+            if args[0].default.is_some() {
+                usage = format!("[{}]", usage);
+            }
+            write!(f, " {}", usage)?;
+        }
+        Ok(())
     }
 }
 
 #[clonable]
 pub trait Value: Any + Debug + Clone {
     fn into_any(self: Box<Self>) -> Box<dyn Any>;
+    fn as_any(&self) -> &dyn Any;
+    fn type_name(&self) -> &'static str;
     fn as_string(self: Box<Self>) -> Result<String, RedisError>;
     fn as_u64(self: Box<Self>) -> Result<u64, RedisError>;
     fn as_i64(self: Box<Self>) -> Result<i64, RedisError>;
     fn as_f64(self: Box<Self>) -> Result<f64, RedisError>;
+    fn as_duration(self: Box<Self>) -> Result<Duration, RedisError>;
+    fn as_memory_size(self: Box<Self>) -> Result<u64, RedisError>;
+    fn as_score_bound(self: Box<Self>) -> Result<ScoreBound, RedisError>;
+    fn as_lex_bound(self: Box<Self>) -> Result<LexBound, RedisError>;
+    fn as_stream_id(self: Box<Self>) -> Result<StreamId, RedisError>;
+    fn as_longitude(self: Box<Self>) -> Result<f64, RedisError>;
+    fn as_latitude(self: Box<Self>) -> Result<f64, RedisError>;
+    fn as_distance_unit(self: Box<Self>) -> Result<DistanceUnit, RedisError>;
+    #[cfg(feature = "json")]
+    fn as_json(self: Box<Self>) -> Result<serde_json::Value, RedisError>;
     fn as_vec(self: Box<Self>) -> Result<Vec<Box<dyn Value>>, RedisError>;
+    fn as_set(self: Box<Self>) -> Result<Set, RedisError>;
+    fn as_pairs(self: Box<Self>) -> Result<Pairs, RedisError>;
     fn as_stringvec(self: Box<Self>) -> Result<Vec<String>, RedisError>;
     fn as_u64vec(self: Box<Self>) -> Result<Vec<u64>, RedisError>;
     fn as_i64vec(self: Box<Self>) -> Result<Vec<i64>, RedisError>;
@@ -231,6 +712,14 @@ impl<T: Any + Debug + Clone> Value for T {
         self
     }
 
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn type_name(&self) -> &'static str {
+        type_name::<T>()
+    }
+
     fn as_string(self: Box<Self>) -> Result<String, RedisError> {
         match self.into_any().downcast::<String>() {
             Ok(d) => Ok(*d),
@@ -271,6 +760,101 @@ impl<T: Any + Debug + Clone> Value for T {
         }
     }
 
+    fn as_duration(self: Box<Self>) -> Result<Duration, RedisError> {
+        let any = self.into_any();
+        if let Some(d) = any.downcast_ref::<Seconds>() {
+            Ok(d.0)
+        } else if let Some(d) = any.downcast_ref::<Milliseconds>() {
+            Ok(d.0)
+        } else {
+            Err(RedisError::String(format!(
+                "Unable to cast {:?} into Duration",
+                any
+            )))
+        }
+    }
+
+    fn as_memory_size(self: Box<Self>) -> Result<u64, RedisError> {
+        match self.into_any().downcast::<MemorySize>() {
+            Ok(d) => Ok(d.0),
+            Err(e) => Err(RedisError::String(format!(
+                "Unable to cast {:?} into MemorySize",
+                e
+            ))),
+        }
+    }
+
+    fn as_score_bound(self: Box<Self>) -> Result<ScoreBound, RedisError> {
+        match self.into_any().downcast::<ScoreBound>() {
+            Ok(d) => Ok(*d),
+            Err(e) => Err(RedisError::String(format!(
+                "Unable to cast {:?} into ScoreBound",
+                e
+            ))),
+        }
+    }
+
+    fn as_lex_bound(self: Box<Self>) -> Result<LexBound, RedisError> {
+        match self.into_any().downcast::<LexBound>() {
+            Ok(d) => Ok(*d),
+            Err(e) => Err(RedisError::String(format!(
+                "Unable to cast {:?} into LexBound",
+                e
+            ))),
+        }
+    }
+
+    fn as_stream_id(self: Box<Self>) -> Result<StreamId, RedisError> {
+        match self.into_any().downcast::<StreamId>() {
+            Ok(d) => Ok(*d),
+            Err(e) => Err(RedisError::String(format!(
+                "Unable to cast {:?} into StreamId",
+                e
+            ))),
+        }
+    }
+
+    fn as_longitude(self: Box<Self>) -> Result<f64, RedisError> {
+        match self.into_any().downcast::<Longitude>() {
+            Ok(d) => Ok(d.0),
+            Err(e) => Err(RedisError::String(format!(
+                "Unable to cast {:?} into Longitude",
+                e
+            ))),
+        }
+    }
+
+    fn as_latitude(self: Box<Self>) -> Result<f64, RedisError> {
+        match self.into_any().downcast::<Latitude>() {
+            Ok(d) => Ok(d.0),
+            Err(e) => Err(RedisError::String(format!(
+                "Unable to cast {:?} into Latitude",
+                e
+            ))),
+        }
+    }
+
+    fn as_distance_unit(self: Box<Self>) -> Result<DistanceUnit, RedisError> {
+        match self.into_any().downcast::<DistanceUnit>() {
+            Ok(d) => Ok(*d),
+            Err(e) => Err(RedisError::String(format!(
+                "Unable to cast {:?} into DistanceUnit",
+                e
+            ))),
+        }
+    }
+
+    #[cfg(feature = "json")]
+    fn as_json(self: Box<Self>) -> Result<serde_json::Value, RedisError> {
+        match self.into_any().downcast::<serde_json::Value>() {
+            Ok(d) => Ok(*d),
+            Err(e) => Err(RedisError::String(format!(
+                "Unable to cast {:?} into JSON",
+                e
+            ))),
+        }
+    }
+
     fn as_vec(self: Box<Self>) -> Result<Vec<Box<dyn Value>>, RedisError> {
         match self.into_any().downcast::<Vec<Box<dyn Value>>>() {
             Ok(d) => Ok(*d),
@@ -281,6 +865,26 @@ impl<T: Any + Debug + Clone> Value for T {
         }
     }
 
+    fn as_set(self: Box<Self>) -> Result<Set, RedisError> {
+        match self.into_any().downcast::<Set>() {
+            Ok(d) => Ok(*d),
+            Err(e) => Err(RedisError::String(format!(
+                "Unable to cast {:?} into Set",
+                e
+            ))),
+        }
+    }
+
+    fn as_pairs(self: Box<Self>) -> Result<Pairs, RedisError> {
+        match self.into_any().downcast::<Pairs>() {
+            Ok(d) => Ok(*d),
+            Err(e) => Err(RedisError::String(format!(
+                "Unable to cast {:?} into pairs",
+                e
+            ))),
+        }
+    }
+
     fn as_stringvec(self: Box<Self>) -> Result<Vec<String>, RedisError> {
         self.as_vec()?
             .into_iter()
@@ -322,6 +926,69 @@ impl<T: Any + Debug + Clone> Value for T {
     }
 }
 
+impl dyn Value {
+    /// Takes the values of an `Array(N)` arg as an array of type `T`, e.g.
+    /// `as_array::<f64, 128>()`.
+    pub fn as_array<T: Any, const N: usize>(self: Box<Self>) -> Result<[T; N], RedisError> {
+        let vals = self
+            .as_vec()?
+            .into_iter()
+            .map(|v| match v.into_any().downcast::<T>() {
+                Ok(d) => Ok(*d),
+                Err(e) => Err(RedisError::String(format!(
+                    "Unable to cast {:?} into {}",
+                    e,
+                    type_name::<T>()
+                ))),
+            })
+            .collect::<Result<Vec<T>, RedisError>>()?;
+        <[T; N]>::try_from(vals).map_err(|v| {
+            RedisError::String(format!(
+                "Unable to cast {} values into an array of {}",
+                v.len(),
+                N
+            ))
+        })
+    }
+
+    /// Takes the values of a `Set` arg as a `HashSet` of type `T`, e.g.
+    /// `as_hash_set::<String>()`.
+    pub fn as_hash_set<T: Any + Eq + Hash>(self: Box<Self>) -> Result<HashSet<T>, RedisError> {
+        self.as_set()?
+            .values
+            .into_iter()
+            .map(|v| match v.into_any().downcast::<T>() {
+                Ok(d) => Ok(*d),
+                Err(e) => Err(RedisError::String(format!(
+                    "Unable to cast {:?} into {}",
+                    e,
+                    type_name::<T>()
+                ))),
+            })
+            .collect()
+    }
+
+    /// Takes the pairs of a `Map` arg as a `HashMap` of its keys of type `K`
+    /// to its values of type `V`, e.g. `as_map::<String, u64>()`.
+    pub fn as_map<K: Any + Eq + Hash, V: Any>(
+        self: Box<Self>,
+    ) -> Result<HashMap<K, V>, RedisError> {
+        let cast = |e: Box<dyn Any>, ty: &str| {
+            RedisError::String(format!("Unable to cast {:?} into {}", e, ty))
+        };
+        self.as_pairs()?
+            .into_iter()
+            .map(|(k, v)| {
+                let k = k.into_any().downcast::<K>();
+                let k = k.map_err(|e| cast(e, type_name::<K>()))?;
+                let v = v.into_any().downcast::<V>();
+                let v = v.map_err(|e| cast(e, type_name::<V>()))?;
+                Ok((*k, *v))
+            })
+            .collect()
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum ArgType {
     Arg,
@@ -332,16 +999,42 @@ pub enum ArgType {
 pub enum Collection {
     Unit,
     Vec,
+    /// A count, then that many values, parsed into a `Set` of the distinct
+    /// ones.
+    Set,
+    /// Exactly the given number of values, without a count.
+    Array(usize),
+    /// A count, then that many pairs of a key of the given data type and a
+    /// value, parsed into `Pairs`. Written `Collection::Map(String)` in
+    /// `command!`.
+    Map(&'static str),
+}
+
+/// What to do with a key given more than once to a `Map` arg, or a value
+/// given more than once to a `Set`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Duplicates {
+    /// The last value of the key wins, like with `HSET`, and repeated values
+    /// of a set are dropped, like with `SADD`.
+    Allow,
+    /// The args are rejected.
+    Reject,
 }
 
 #[derive(Debug)]
 pub struct Arg {
     pub arg: &'static str,
+    /// Keyword of a kwarg, the name of the arg by default.
+    pub token: &'static str,
     pub desc: &'static str,
     pub arg_type: ArgType,
     pub type_name: &'static str,
     pub kind: Collection,
     pub default: Option<Box<dyn Value>>,
+    pub max_len: Option<usize>,
+    pub duplicates: Duplicates,
+    #[cfg(feature = "json")]
+    pub schema: Option<json::Schema>,
 }
 
 impl Arg {
@@ -355,43 +1048,285 @@ impl Arg {
     ) -> Self {
         Arg {
             arg,
+            token: arg,
             desc,
             arg_type,
             type_name,
             kind,
             default,
+            max_len: None,
+            duplicates: Duplicates::Allow,
+            #[cfg(feature = "json")]
+            schema: None,
         }
     }
-}
 
-impl std::cmp::PartialEq for Arg {
-    fn eq(&self, other: &Self) -> bool {
-        self.arg == other.arg
-            && self.arg_type == other.arg_type
-            && self.type_name == other.type_name
-            && self.kind == other.kind
-            && self.default.is_none() == other.default.is_none()
+    /// Sets the keyword of a kwarg, so that it can differ from the name in
+    /// the parse result. Kwargs sharing a keyword are given together, e.g.
+    /// `LIMIT offset count` for kwargs `limit_offset` and `limit_count`.
+    /// Keywords are matched in lowercase, so `token` must be lowercase.
+    pub fn token(mut self, token: &'static str) -> Self {
+        self.token = token;
+        self
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::{Arg, ArgType, Collection, Command};
+    /// Limits the number of values of a `Vec` or `Set` arg, or pairs of a
+    /// `Map`.
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = Some(max_len);
+        self
+    }
 
-    extern crate redis_module;
+    /// Sets what to do with keys given more than once to a `Map` arg, or
+    /// values given more than once to a `Set`.
+    pub fn duplicates(mut self, duplicates: Duplicates) -> Self {
+        self.duplicates = duplicates;
+        self
+    }
 
-    #[test]
-    fn macro_test() {
-        let cmd = command! {
-            name: "test",
-            desc: "foo",
-            args: [
-                ["stringarg", "a string", ArgType::Arg, String, Collection::Unit, None],
-                ["uintarg", "an uint", ArgType::Kwarg, u64, Collection::Unit, Some(Box::new(1_u64))],
-                ["intarg", "an int", ArgType::Kwarg, i64, Collection::Unit, Some(Box::new(1_i64))],
-                ["floatarg", "a float", ArgType::Kwarg, f64, Collection::Unit, Some(Box::new(1_f64))],
-            ],
-        };
+    /// Checks that the JSON documents given for this arg deserialize into
+    /// the type of the schema.
+    #[cfg(feature = "json")]
+    pub fn schema(mut self, schema: json::Schema) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Minimum number of raw args needed to fill this arg, excluding the
+    /// keyword of a kwarg.
+    fn min_args(&self) -> i64 {
+        match self.kind {
+            Collection::Unit => 1,
+            // the count, which may be 0
+            Collection::Vec | Collection::Set | Collection::Map(_) => 1,
+            Collection::Array(len) => len as i64,
+        }
+    }
+
+    /// Checks that the default has the declared data type. `Vec` defaults
+    /// hold boxed values like parsed `Vec`s, each of the declared data type,
+    /// as do `Array` defaults of the declared length. `Set` and `Map`
+    /// defaults are a `Set` and `Pairs` with keys of the key type.
+    fn check_default(&self) -> Result<(), RedisError> {
+        let default = match &self.default {
+            Some(d) => d,
+            None => return Ok(()),
+        };
+
+        let ok = match self.kind {
+            Collection::Unit => (**default).type_name() == self.type_name,
+            Collection::Vec => match (**default).as_any().downcast_ref::<Vec<Box<dyn Value>>>() {
+                Some(v) => v.iter().all(|x| (**x).type_name() == self.type_name),
+                None => false,
+            },
+            Collection::Set => match (**default).as_any().downcast_ref::<Set>() {
+                Some(s) => s.values.iter().all(|x| (**x).type_name() == self.type_name),
+                None => false,
+            },
+            Collection::Array(len) => {
+                match (**default).as_any().downcast_ref::<Vec<Box<dyn Value>>>() {
+                    Some(v) => {
+                        v.len() == len && v.iter().all(|x| (**x).type_name() == self.type_name)
+                    }
+                    None => false,
+                }
+            }
+            Collection::Map(key_type) => match (**default).as_any().downcast_ref::<Pairs>() {
+                Some(p) => p.iter().all(|(k, v)| {
+                    (**k).type_name() == key_type && (**v).type_name() == self.type_name
+                }),
+                None => false,
+            },
+        };
+
+        if ok {
+            Ok(())
+        } else {
+            let expected = match self.kind {
+                Collection::Unit => self.type_name.to_owned(),
+                Collection::Vec => format!("Vec<Box<dyn Value>> of {}", self.type_name),
+                Collection::Set => format!("Set of {}", self.type_name),
+                Collection::Array(len) => {
+                    format!("Vec<Box<dyn Value>> of {} {}", len, self.type_name)
+                }
+                Collection::Map(key_type) => {
+                    format!("Pairs of {} keys and {} values", key_type, self.type_name)
+                }
+            };
+            Err(RedisError::String(format!(
+                "Default of {} should be {}, got {:?} of type {}",
+                self.arg,
+                expected,
+                default,
+                (**default).type_name()
+            )))
+        }
+    }
+
+    /// Pushes the raw args of the parsed value of this arg, with `Vec`s,
+    /// `Set`s and `Map`s prefixed by their count.
+    fn push_raw_args(
+        &self,
+        parsed: &HashMap<&'static str, Box<dyn Value>>,
+        argv: &mut Vec<String>,
+    ) -> Result<(), RedisError> {
+        match parsed.get(self.arg) {
+            Some(val) => self.push_raw_value(&**val, argv),
+            None => Err(RedisError::String(format!("{} is required", self.arg))),
+        }
+    }
+
+    /// Pushes the raw args of a value of this arg, e.g. its default.
+    pub(crate) fn push_raw_value(
+        &self,
+        val: &dyn Value,
+        argv: &mut Vec<String>,
+    ) -> Result<(), RedisError> {
+        let mismatch = || {
+            RedisError::String(format!(
+                "{} should be of type {}, got {:?}",
+                self.arg, self.type_name, val
+            ))
+        };
+
+        match self.kind {
+            Collection::Unit => {
+                if val.type_name() != self.type_name {
+                    return Err(mismatch());
+                }
+                argv.push(raw_arg(val));
+            }
+            Collection::Vec | Collection::Set | Collection::Array(_) => {
+                let vals = match self.kind {
+                    Collection::Set => val.as_any().downcast_ref::<Set>().map(|s| &s.values),
+                    _ => val.as_any().downcast_ref::<Vec<Box<dyn Value>>>(),
+                };
+                let vals = vals.ok_or_else(mismatch)?;
+                match self.kind {
+                    // arrays have a fixed length rather than a count
+                    Collection::Array(len) if vals.len() != len => return Err(mismatch()),
+                    Collection::Array(_) => (),
+                    _ => argv.push(vals.len().to_string()),
+                }
+                for v in vals.iter() {
+                    if (**v).type_name() != self.type_name {
+                        return Err(mismatch());
+                    }
+                    argv.push(raw_arg(&**v));
+                }
+            }
+            Collection::Map(key_type) => {
+                let pairs = match val.as_any().downcast_ref::<Pairs>() {
+                    Some(p) => p,
+                    None => return Err(mismatch()),
+                };
+                argv.push(pairs.len().to_string());
+                for (k, v) in pairs.iter() {
+                    if (**k).type_name() != key_type || (**v).type_name() != self.type_name {
+                        return Err(mismatch());
+                    }
+                    argv.push(raw_arg(&**k));
+                    argv.push(raw_arg(&**v));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Usage fragment of the values of this arg, without its keyword.
+    fn value_usage(&self) -> String {
+        match self.kind {
+            Collection::Unit => self.arg.to_owned(),
+            Collection::Vec | Collection::Set => {
+                format!("count {arg} [{arg} ...]", arg = self.arg)
+            }
+            Collection::Array(len) => array_usage(self.arg, len),
+            Collection::Map(_) => format!("count field {arg} [field {arg} ...]", arg = self.arg),
+        }
+    }
+
+    /// Usage fragment of this positional arg, as shown in the command's
+    /// usage line.
+    fn usage(&self) -> String {
+        let mut usage = self.value_usage();
+        if self.default.is_some() {
+            usage = format!("[{}]", usage);
+        }
+        usage
+    }
+}
+
+/// Usage fragment of the values of an `Array` arg, e.g. `vec_1 ... vec_128`.
+fn array_usage(arg: &str, len: usize) -> String {
+    match len {
+        1 => arg.to_owned(),
+        2 => format!("{arg}_1 {arg}_2", arg = arg),
+        _ => format!("{arg}_1 ... {arg}_{len}", arg = arg, len = len),
+    }
+}
+
+/// Formats a value the way it is passed as a raw arg, so that parsing the
+/// result gives back the same value. Values of other types fall back to
+/// their debug representation.
+pub(crate) fn raw_arg(val: &dyn Value) -> String {
+    let any = val.as_any();
+    if let Some(s) = any.downcast_ref::<String>() {
+        s.clone()
+    } else if let Some(n) = any.downcast_ref::<u64>() {
+        n.to_string()
+    } else if let Some(n) = any.downcast_ref::<i64>() {
+        n.to_string()
+    } else if let Some(n) = any.downcast_ref::<f64>() {
+        n.to_string()
+    } else if let Some(raw) = types::raw(val) {
+        raw
+    } else {
+        format!("{:?}", val)
+    }
+}
+
+impl std::cmp::PartialEq for Arg {
+    fn eq(&self, other: &Self) -> bool {
+        #[cfg(feature = "json")]
+        if self.schema != other.schema {
+            return false;
+        }
+        self.arg == other.arg
+            && self.token == other.token
+            && self.arg_type == other.arg_type
+            && self.type_name == other.type_name
+            && self.kind == other.kind
+            && self.default.is_none() == other.default.is_none()
+            && self.max_len == other.max_len
+            && self.duplicates == other.duplicates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Arg, ArgType, Collection, Command, DistanceUnit, Duplicates, KwargPolicy, Latitude,
+        Longitude, Milliseconds, Pairs, Seconds, Set, Value,
+    };
+    use std::collections::{HashMap, HashSet};
+    use std::time::Duration;
+
+    extern crate redis_module;
+    use redis_module::RedisError;
+
+    #[test]
+    fn macro_test() {
+        let cmd = command! {
+            name: "test",
+            desc: "foo",
+            args: [
+                ["stringarg", "a string", ArgType::Arg, String, Collection::Unit, None],
+                ["uintarg", "an uint", ArgType::Kwarg, u64, Collection::Unit, Some(Box::new(1_u64))],
+                ["intarg", "an int", ArgType::Kwarg, i64, Collection::Unit, Some(Box::new(1_i64))],
+                ["floatarg", "a float", ArgType::Kwarg, f64, Collection::Unit, Some(Box::new(1_f64))],
+            ],
+        };
 
         let mut exp = Command::new("test", "foo");
         let arg1 = Arg::new(
@@ -480,6 +1415,195 @@ mod tests {
         );
     }
 
+    #[test]
+    fn try_add_arg_test() {
+        let mut cmd = Command::new("test", "foo");
+
+        let arg = Arg::new(
+            "n",
+            "a number",
+            ArgType::Kwarg,
+            std::any::type_name::<u64>(),
+            Collection::Unit,
+            Some(Box::new(1_i64)),
+        );
+        match cmd.try_add_arg(arg) {
+            Err(RedisError::String(s)) => {
+                assert_eq!(s, "Default of n should be u64, got 1 of type i64");
+            }
+            res => panic!("Bad result: {:?}", res),
+        }
+
+        let default: Vec<Box<dyn Value>> = vec![Box::new(1_u64), Box::new(2_i64)];
+        let arg = Arg::new(
+            "vec1",
+            "a vec",
+            ArgType::Kwarg,
+            std::any::type_name::<u64>(),
+            Collection::Vec,
+            Some(Box::new(default)),
+        );
+        assert!(cmd.try_add_arg(arg).is_err());
+
+        let arg = Arg::new(
+            "vec2",
+            "a vec",
+            ArgType::Kwarg,
+            std::any::type_name::<u64>(),
+            Collection::Vec,
+            Some(Box::new(vec![1_u64])),
+        );
+        assert!(cmd.try_add_arg(arg).is_err());
+
+        let default: Vec<Box<dyn Value>> = vec![Box::new(1_u64), Box::new(2_u64)];
+        let arg = Arg::new(
+            "vec3",
+            "a vec",
+            ArgType::Kwarg,
+            std::any::type_name::<u64>(),
+            Collection::Vec,
+            Some(Box::new(default)),
+        );
+        assert!(cmd.try_add_arg(arg).is_ok());
+        assert!(cmd.kwargs.contains_key("vec3"));
+    }
+
+    #[test]
+    fn literal_default_test() {
+        fn limit() -> Option<Box<dyn Value>> {
+            Some(Box::new(10_u64))
+        }
+
+        // untyped literals get the declared type, other defaults are
+        // checked when they are added
+        let cmd = command! {
+            name: "test",
+            desc: "test",
+            args: [
+                ["n", "a number", ArgType::Kwarg, u64, Collection::Unit, Some(Box::new(1))],
+                ["offset", "an offset", ArgType::Kwarg, i64, Collection::Unit, Some(Box::new(-2))],
+                ["ratio", "a ratio", ArgType::Kwarg, f64, Collection::Unit, Some(Box::new(0.5))],
+                ["limit", "a limit", ArgType::Kwarg, u64, Collection::Unit, limit()],
+            ],
+        };
+
+        let mut parsed = cmd.parse_args(vec!["test".to_owned()]).unwrap();
+        assert_eq!(parsed.remove("n").unwrap().as_u64().unwrap(), 1);
+        assert_eq!(parsed.remove("offset").unwrap().as_i64().unwrap(), -2);
+        assert!((parsed.remove("ratio").unwrap().as_f64().unwrap() - 0.5).abs() < f64::EPSILON);
+        assert_eq!(parsed.remove("limit").unwrap().as_u64().unwrap(), 10);
+    }
+
+    #[test]
+    fn try_command_test() {
+        fn limit() -> Option<Box<dyn Value>> {
+            Some(Box::new(10_i64))
+        }
+
+        let cmd = try_command! {
+            name: "test",
+            desc: "test",
+            args: [
+                ["n", "a number", ArgType::Kwarg, u64, Collection::Unit, Some(Box::new(1))],
+                ["limit", "a limit", ArgType::Kwarg, u64, Collection::Unit, limit()],
+            ],
+        };
+        match cmd {
+            Err(RedisError::String(s)) => {
+                assert_eq!(s, "Default of limit should be u64, got 10 of type i64");
+            }
+            res => panic!("Bad result: {:?}", res.map(|c| c.to_string())),
+        }
+
+        let cmd = try_command! {
+            name: "test",
+            desc: "test",
+            args: [
+                ["n", "a number", ArgType::Kwarg, u64, Collection::Unit, Some(Box::new(1))],
+            ],
+            examples: ["test n 2"],
+        };
+        assert!(cmd.unwrap().check_examples().is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid command test: Default of limit should be u64")]
+    fn command_panic_test() {
+        fn limit() -> Option<Box<dyn Value>> {
+            Some(Box::new(10_i64))
+        }
+
+        command! {
+            name: "test",
+            desc: "test",
+            args: [
+                ["limit", "a limit", ArgType::Kwarg, u64, Collection::Unit, limit()],
+            ],
+        };
+    }
+
+    #[test]
+    fn arity_test() {
+        let cmd = command! {
+            name: "test",
+            desc: "foo",
+            args: [
+                ["foo", "bar", ArgType::Arg, String, Collection::Unit, None],
+                ["baz", "qux", ArgType::Arg, u64, Collection::Unit, None],
+            ],
+        };
+        assert_eq!(cmd.arity(), 3);
+
+        let cmd = command! {
+            name: "test",
+            desc: "foo",
+            args: [
+                ["foo", "bar", ArgType::Arg, String, Collection::Unit, None],
+                ["vec1", "a vec", ArgType::Arg, u64, Collection::Vec, None],
+            ],
+        };
+        assert_eq!(cmd.arity(), -3);
+
+        let cmd = command! {
+            name: "test",
+            desc: "foo",
+            args: [
+                ["foo", "bar", ArgType::Arg, String, Collection::Unit, None],
+                ["optional", "baz", ArgType::Arg, String, Collection::Unit, Some(Box::new("foo".to_owned()))],
+                ["n", "a number", ArgType::Kwarg, u64, Collection::Unit, Some(Box::new(1_u64))],
+                ["vec1", "a vec", ArgType::Kwarg, i64, Collection::Vec, None],
+            ],
+        };
+        assert_eq!(cmd.arity(), -4);
+
+        let raw_args = vec!["test".to_owned(), "bar".to_owned(), "vec1".to_owned()];
+        match cmd.parse_args(raw_args) {
+            Err(RedisError::String(s)) => {
+                assert_eq!(s, "wrong number of arguments for 'test' command");
+            }
+            res => panic!("Bad result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn usage_test() {
+        let cmd = command! {
+            name: "hello.foo",
+            desc: "foo",
+            args: [
+                ["input", "a string", ArgType::Arg, String, Collection::Unit, None],
+                ["optional", "another string", ArgType::Arg, String, Collection::Unit, Some(Box::new("baz".to_owned()))],
+                ["vec1", "a vec", ArgType::Kwarg, i64, Collection::Vec, None],
+                ["n", "a number", ArgType::Kwarg, u64, Collection::Unit, Some(Box::new(1_u64))],
+                ["vec2", "another vec", ArgType::Arg, u64, Collection::Vec, None],
+            ],
+        };
+        assert_eq!(
+            cmd.to_string(),
+            "HELLO.FOO input count vec2 [vec2 ...] [optional] [N n] VEC1 count vec1 [vec1 ...]"
+        );
+    }
+
     #[test]
     fn parse_vec_args_test() {
         let cmd = command! {
@@ -533,4 +1657,638 @@ mod tests {
             "buzz".to_owned()
         );
     }
+
+    #[test]
+    fn to_argv_test() {
+        let cmd = command! {
+            name: "hello.foo",
+            desc: "foo",
+            args: [
+                ["input", "a string", ArgType::Arg, String, Collection::Unit, None],
+                ["optional", "another string", ArgType::Arg, String, Collection::Unit, Some(Box::new("baz".to_owned()))],
+                ["vec1", "a vec", ArgType::Kwarg, i64, Collection::Vec, None],
+                ["n", "a number", ArgType::Kwarg, u64, Collection::Unit, Some(Box::new(1_u64))],
+                ["x", "a float", ArgType::Kwarg, f64, Collection::Unit, Some(Box::new(0.5_f64))],
+            ],
+        };
+
+        let raw_args: Vec<String> = vec!["HELLO.FOO", "bar", "vec1", "2", "-1", "3", "x", "1.25"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let parsed = cmd.parse_args(raw_args).unwrap();
+        let argv = cmd.to_argv(&parsed).unwrap();
+        assert_eq!(
+            argv,
+            vec![
+                "hello.foo",
+                "bar",
+                "baz",
+                "N",
+                "1",
+                "VEC1",
+                "2",
+                "-1",
+                "3",
+                "X",
+                "1.25"
+            ]
+        );
+        assert_eq!(
+            format!("{:?}", cmd.parse_args(argv).unwrap().get("vec1")),
+            format!("{:?}", parsed.get("vec1"))
+        );
+
+        let mut parsed = parsed;
+        parsed.remove("input");
+        assert!(cmd.to_argv(&parsed).is_err());
+
+        parsed.insert("input", Box::new(1_u64));
+        assert!(cmd.to_argv(&parsed).is_err());
+
+        // an optional arg matching a keyword can't be given positionally
+        parsed.insert("input", Box::new("bar".to_owned()));
+        parsed.insert("optional", Box::new("N".to_owned()));
+        assert!(cmd.to_argv(&parsed).is_err());
+    }
+
+    #[test]
+    fn limits_test() {
+        let cmd = command! {
+            name: "test",
+            desc: "test desc",
+            args: [
+                ["vec1", "a vec", ArgType::Arg, u64, Collection::Vec, None, max_len: 2],
+                ["vec2", "a vec", ArgType::Kwarg, String, Collection::Vec, None],
+            ],
+        }
+        .max_vec_len(3)
+        .max_args(10);
+        assert_eq!(cmd.required_args[0].max_len, Some(2));
+
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+
+        let parsed = cmd.parse_args(args(&["test", "2", "1", "2", "vec2", "3", "a", "b", "c"]));
+        assert!(parsed.is_ok());
+
+        let parsed = cmd.parse_args(args(&["test", "3", "1", "2", "3", "vec2", "0"]));
+        match parsed {
+            Err(RedisError::String(s)) => assert_eq!(s, "vec1 can have at most 2 values"),
+            _ => panic!("Bad result: {:?}", parsed),
+        }
+
+        let parsed = cmd.parse_args(args(&["test", "0", "vec2", "4", "a", "b", "c", "d"]));
+        match parsed {
+            Err(RedisError::String(s)) => assert_eq!(s, "vec2 can have at most 3 values"),
+            _ => panic!("Bad result: {:?}", parsed),
+        }
+
+        let parsed = cmd.parse_args(args(&[
+            "test", "0", "vec2", "1", "a", "b", "c", "d", "e", "f", "g",
+        ]));
+        match parsed {
+            Err(RedisError::String(s)) => assert_eq!(
+                s,
+                "too many arguments for 'test' command, at most 10 are allowed"
+            ),
+            _ => panic!("Bad result: {:?}", parsed),
+        }
+
+        // a huge count is rejected before reserving anything
+        let cmd = command! {
+            name: "test",
+            desc: "test desc",
+            args: [
+                ["vec1", "a vec", ArgType::Kwarg, u64, Collection::Vec, None],
+            ],
+        };
+        let parsed = cmd.parse_args(args(&["test", "vec1", "9999999999999"]));
+        assert!(matches!(parsed, Err(RedisError::WrongArity)));
+    }
+
+    #[test]
+    fn kwarg_policy_test() {
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+
+        let cmd = command! {
+            name: "test",
+            desc: "test desc",
+            args: [
+                ["key", "a key", ArgType::Arg, String, Collection::Unit, Some(Box::new("k".to_owned()))],
+                ["n", "a number", ArgType::Kwarg, u64, Collection::Unit, Some(Box::new(1_u64))],
+            ],
+        };
+        assert_eq!(cmd.kwarg_policy, KwargPolicy::Greedy);
+        assert!(cmd.parse_args(args(&["test", "n", "2"])).is_ok());
+        assert!(cmd.parse_args(args(&["test", "n", "n", "2"])).is_err());
+
+        let cmd = command! {
+            name: "test",
+            desc: "test desc",
+            kwarg_policy: KwargPolicy::Positional,
+            args: [
+                ["key", "a key", ArgType::Arg, String, Collection::Unit, Some(Box::new("k".to_owned()))],
+                ["n", "a number", ArgType::Kwarg, u64, Collection::Unit, Some(Box::new(1_u64))],
+            ],
+        };
+        assert_eq!(cmd.kwarg_policy, KwargPolicy::Positional);
+        let mut parsed = cmd.parse_args(args(&["test", "n", "n", "2"])).unwrap();
+        assert_eq!(parsed.remove("key").unwrap().as_string().unwrap(), "n");
+        assert_eq!(parsed.remove("n").unwrap().as_u64().unwrap(), 2);
+        assert!(cmd.parse_args(args(&["test", "n", "2"])).is_err());
+
+        let cmd = command! {
+            name: "test",
+            desc: "test desc",
+            kwarg_policy: KwargPolicy::Terminator,
+            args: [
+                ["key", "a key", ArgType::Arg, String, Collection::Unit, Some(Box::new("k".to_owned()))],
+                ["n", "a number", ArgType::Kwarg, u64, Collection::Unit, Some(Box::new(1_u64))],
+            ],
+        };
+        assert_eq!(cmd.to_string(), "TEST [key] [--] [N n]");
+        let mut parsed = cmd.parse_args(args(&["test", "--", "n", "2"])).unwrap();
+        assert_eq!(parsed.remove("key").unwrap().as_string().unwrap(), "k");
+        assert_eq!(parsed.remove("n").unwrap().as_u64().unwrap(), 2);
+        let mut parsed = cmd.parse_args(args(&["test", "n", "n", "2"])).unwrap();
+        assert_eq!(parsed.remove("key").unwrap().as_string().unwrap(), "n");
+        assert!(cmd.parse_args(args(&["test", "n", "--", "n", "2"])).is_ok());
+        assert!(cmd.parse_args(args(&["test", "--", "--"])).is_err());
+
+        // values matching keywords are given positionally
+        let parsed = cmd.parse_args(args(&["test", "n"])).unwrap();
+        assert_eq!(cmd.to_argv(&parsed).unwrap(), vec!["test", "n", "N", "1"]);
+
+        // optional args can only be left out before required kwargs with a
+        // terminator, which counts towards the arity
+        let key = || {
+            Arg::new(
+                "key",
+                "a key",
+                ArgType::Arg,
+                std::any::type_name::<String>(),
+                Collection::Unit,
+                Some(Box::new("k".to_owned())),
+            )
+        };
+        let n = || {
+            Arg::new(
+                "n",
+                "a number",
+                ArgType::Kwarg,
+                "u64",
+                Collection::Unit,
+                None,
+            )
+        };
+        let mut cmd = Command::new("test", "test desc").kwarg_policy(KwargPolicy::Positional);
+        cmd.add_arg(n());
+        match cmd.try_add_arg(key()) {
+            Err(RedisError::String(s)) => assert_eq!(
+                s,
+                "key can't be optional with KwargPolicy::Positional, as it has to be given before the required kwarg n"
+            ),
+            res => panic!("Bad result: {:?}", res),
+        }
+        let mut cmd = Command::new("test", "test desc").kwarg_policy(KwargPolicy::Positional);
+        cmd.add_arg(key());
+        assert!(cmd.try_add_arg(n()).is_err());
+
+        let mut cmd = Command::new("test", "test desc").kwarg_policy(KwargPolicy::Terminator);
+        cmd.add_arg(key());
+        cmd.add_arg(n());
+        assert_eq!(cmd.arity(), -4);
+        assert!(cmd.parse_args(args(&["test", "--", "n", "2"])).is_ok());
+        assert!(cmd.parse_args(args(&["test", "n", "2"])).is_err());
+
+        // the policy is checked against the args added before
+        match cmd.try_kwarg_policy(KwargPolicy::Positional) {
+            Err(RedisError::String(s)) => assert_eq!(
+                s,
+                "key can't be optional with KwargPolicy::Positional, as it has to be given before the required kwarg n"
+            ),
+            res => panic!("Bad result: {:?}", res),
+        }
+        let mut cmd = Command::new("test", "test desc");
+        cmd.add_arg(key());
+        let cmd = cmd.kwarg_policy(KwargPolicy::Positional);
+        assert!(cmd.parse_args(args(&["test", "n"])).is_ok());
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Invalid kwarg policy for test: key can't be optional with KwargPolicy::Positional"
+    )]
+    fn kwarg_policy_panic_test() {
+        let mut cmd = Command::new("test", "test desc");
+        cmd.add_arg(Arg::new(
+            "key",
+            "a key",
+            ArgType::Arg,
+            std::any::type_name::<String>(),
+            Collection::Unit,
+            Some(Box::new("k".to_owned())),
+        ));
+        cmd.add_arg(Arg::new(
+            "n",
+            "a number",
+            ArgType::Kwarg,
+            "u64",
+            Collection::Unit,
+            None,
+        ));
+        cmd.kwarg_policy(KwargPolicy::Positional);
+    }
+
+    #[test]
+    fn token_test() {
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+
+        let cmd = command! {
+            name: "test",
+            desc: "test desc",
+            args: [
+                ["key", "a key", ArgType::Arg, String, Collection::Unit, None],
+                ["limit_offset", "an offset", ArgType::Kwarg, u64, Collection::Unit, Some(Box::new(0_u64)), token: "limit"],
+                ["limit_count", "a count", ArgType::Kwarg, u64, Collection::Unit, Some(Box::new(10_u64)), token: "limit"],
+                ["by", "a pattern", ArgType::Kwarg, String, Collection::Unit, None, token: "sortby"],
+            ],
+        };
+        assert_eq!(cmd.keywords["limit"], vec!["limit_offset", "limit_count"]);
+        assert_eq!(
+            cmd.to_string(),
+            "TEST key [LIMIT limit_offset limit_count] SORTBY by"
+        );
+        assert_eq!(cmd.arity(), -4);
+
+        let mut parsed = cmd
+            .parse_args(args(&["test", "k", "limit", "5", "20", "sortby", "w_*"]))
+            .unwrap();
+        assert_eq!(
+            cmd.to_argv(&parsed).unwrap(),
+            vec!["test", "k", "LIMIT", "5", "20", "SORTBY", "w_*"]
+        );
+        assert_eq!(parsed.remove("limit_offset").unwrap().as_u64().unwrap(), 5);
+        assert_eq!(parsed.remove("limit_count").unwrap().as_u64().unwrap(), 20);
+        assert_eq!(parsed.remove("by").unwrap().as_string().unwrap(), "w_*");
+
+        let mut parsed = cmd
+            .parse_args(args(&["test", "k", "sortby", "w_*"]))
+            .unwrap();
+        assert_eq!(parsed.remove("limit_count").unwrap().as_u64().unwrap(), 10);
+
+        // the name isn't a keyword
+        assert!(cmd.parse_args(args(&["test", "k", "by", "w_*"])).is_err());
+        assert!(matches!(
+            cmd.parse_args(args(&["test", "k", "sortby", "w_*", "limit", "5"])),
+            Err(RedisError::WrongArity)
+        ));
+
+        let mut cmd = Command::new("test", "test desc");
+        cmd.add_arg(
+            Arg::new(
+                "limit_offset",
+                "an offset",
+                ArgType::Kwarg,
+                "u64",
+                Collection::Unit,
+                None,
+            )
+            .token("limit"),
+        );
+        let res = cmd.try_add_arg(
+            Arg::new(
+                "limit_count",
+                "a count",
+                ArgType::Kwarg,
+                "u64",
+                Collection::Unit,
+                Some(Box::new(10_u64)),
+            )
+            .token("limit"),
+        );
+        assert!(res.is_err());
+
+        let kwarg = |name: &'static str, token: &'static str| {
+            Arg::new(
+                name,
+                "a number",
+                ArgType::Kwarg,
+                "u64",
+                Collection::Unit,
+                None,
+            )
+            .token(token)
+        };
+        match cmd.try_add_arg(kwarg("offset", "LIMIT")) {
+            Err(RedisError::String(s)) => assert_eq!(
+                s,
+                "keyword LIMIT can never be matched, keywords are matched in lowercase"
+            ),
+            res => panic!("Bad result: {:?}", res),
+        }
+        match cmd.try_add_arg(kwarg("limit_offset", "offset")) {
+            Err(RedisError::String(s)) => assert_eq!(s, "duplicate arg name limit_offset"),
+            res => panic!("Bad result: {:?}", res),
+        }
+        assert_eq!(cmd.keywords["limit"], vec!["limit_offset"]);
+        assert!(!cmd.keywords.contains_key("offset"));
+    }
+
+    #[test]
+    fn duration_test() {
+        let cmd = command! {
+            name: "test",
+            desc: "test desc",
+            args: [
+                ["key", "a key", ArgType::Arg, String, Collection::Unit, None],
+                ["ex", "a ttl", ArgType::Kwarg, Seconds, Collection::Unit, Some(Box::new(Seconds(Duration::from_secs(60))))],
+                ["px", "a ttl", ArgType::Kwarg, Milliseconds, Collection::Vec, None],
+            ],
+        };
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+
+        let mut parsed = cmd
+            .parse_args(args(&["test", "k", "px", "2", "500", "10s"]))
+            .unwrap();
+        assert_eq!(
+            parsed.remove("ex").unwrap().as_duration().unwrap(),
+            Duration::from_secs(60)
+        );
+        assert_eq!(
+            parsed
+                .remove("px")
+                .unwrap()
+                .as_vec()
+                .unwrap()
+                .into_iter()
+                .map(|d| d.as_duration().unwrap())
+                .collect::<Vec<Duration>>(),
+            vec![Duration::from_millis(500), Duration::from_secs(10)]
+        );
+
+        let parsed = cmd
+            .parse_args(args(&["test", "k", "ex", "1500ms", "px", "0"]))
+            .unwrap();
+        assert_eq!(
+            cmd.to_argv(&parsed).unwrap(),
+            args(&["test", "k", "EX", "1500ms", "PX", "0"])
+        );
+
+        let res = cmd.parse_args(args(&["test", "k", "ex", "-5", "px", "0"]));
+        assert_eq!(
+            format!("{:?}", res.unwrap_err()),
+            format!(
+                "{:?}",
+                RedisError::String("invalid expire time in 'test' command".to_owned())
+            )
+        );
+    }
+
+    #[test]
+    fn coordinates_test() {
+        let cmd = command! {
+            name: "geosearch",
+            desc: "test desc",
+            args: [
+                ["key", "a key", ArgType::Arg, String, Collection::Unit, None],
+                ["lon", "a longitude", ArgType::Arg, Longitude, Collection::Unit, None],
+                ["lat", "a latitude", ArgType::Arg, Latitude, Collection::Unit, None],
+                ["from_lon", "a longitude", ArgType::Kwarg, Longitude, Collection::Unit, Some(Box::new(Longitude(0.0))), token: "fromlonlat"],
+                ["from_lat", "a latitude", ArgType::Kwarg, Latitude, Collection::Unit, Some(Box::new(Latitude(0.0))), token: "fromlonlat"],
+                ["radius", "a radius", ArgType::Kwarg, f64, Collection::Unit, Some(Box::new(1_f64)), token: "byradius"],
+                ["unit", "a unit", ArgType::Kwarg, DistanceUnit, Collection::Unit, Some(Box::new(DistanceUnit::M)), token: "byradius"],
+                ["lons", "some longitudes", ArgType::Kwarg, Longitude, Collection::Vec, Some(Box::new(Vec::<Box<dyn Value>>::new()))],
+                ["lats", "some latitudes", ArgType::Kwarg, Latitude, Collection::Set, Some(Box::new(Set::default()))],
+                ["max_lat", "a latitude", ArgType::Kwarg, Latitude, Collection::Unit, Some(Box::new(Latitude(85.0)))],
+            ],
+        };
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+        let err = |a: &[&str]| format!("{:?}", cmd.parse_args(args(a)).unwrap_err());
+
+        let mut parsed = cmd
+            .parse_args(args(&[
+                "geosearch",
+                "k",
+                "-180",
+                "85.05112878",
+                "fromlonlat",
+                "15",
+                "37",
+                "byradius",
+                "200",
+                "KM",
+            ]))
+            .unwrap();
+        assert_eq!(
+            parsed.remove("lon").unwrap().as_longitude().unwrap(),
+            -180.0
+        );
+        assert_eq!(
+            parsed.remove("from_lat").unwrap().as_latitude().unwrap(),
+            37.0
+        );
+        assert_eq!(
+            parsed.remove("unit").unwrap().as_distance_unit().unwrap(),
+            DistanceUnit::Km
+        );
+
+        assert!(err(&["geosearch", "k", "200", "10"])
+            .contains("invalid longitude,latitude pair 200.000000,10.000000"));
+        assert!(err(&["geosearch", "k", "10", "-86"])
+            .contains("invalid longitude,latitude pair 10.000000,-86.000000"));
+        assert!(err(&["geosearch", "k", "0", "0", "fromlonlat", "15", "90"])
+            .contains("invalid longitude,latitude pair 15.000000,90.000000"));
+        assert!(
+            err(&["geosearch", "k", "0", "0", "byradius", "1", "yd"]).contains("unsupported unit")
+        );
+
+        // coordinates that aren't part of a pair are reported on their own
+        assert!(err(&["geosearch", "k", "0", "0", "lons", "2", "1", "-200"])
+            .contains("invalid longitude -200.000000\""));
+        assert!(err(&["geosearch", "k", "0", "0", "lats", "2", "1", "86"])
+            .contains("invalid latitude 86.000000\""));
+        assert!(err(&["geosearch", "k", "0", "0", "max_lat", "-90"])
+            .contains("invalid latitude -90.000000\""));
+        assert!(cmd
+            .parse_args(args(&["geosearch", "k", "0", "0", "lons", "1", "86"]))
+            .is_ok());
+    }
+
+    #[test]
+    fn map_test() {
+        let cmd = command! {
+            name: "test",
+            desc: "test desc",
+            args: [
+                ["attrs", "some attributes", ArgType::Arg, String, Collection::Map(u64), None],
+                ["tags", "some tags", ArgType::Kwarg, i64, Collection::Map(String), Some(Box::new(Pairs::new())), max_len: 2, duplicates: Duplicates::Reject],
+            ],
+        };
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+        assert_eq!(cmd.arity(), -2);
+        assert_eq!(
+            cmd.to_string(),
+            "TEST count field attrs [field attrs ...] [TAGS count field tags [field tags ...]]"
+        );
+
+        // the last value of a key wins, at the position the key was first
+        // given
+        let parsed = cmd
+            .parse_args(args(&[
+                "test", "3", "2", "b", "01", "a", "2", "c", "tags", "1", "x", "-1",
+            ]))
+            .unwrap();
+        assert_eq!(
+            cmd.to_argv(&parsed).unwrap(),
+            args(&["test", "2", "2", "c", "1", "a", "TAGS", "1", "x", "-1"])
+        );
+
+        let mut parsed = parsed;
+        let attrs = parsed
+            .remove("attrs")
+            .unwrap()
+            .as_map::<u64, String>()
+            .unwrap();
+        let mut exp = HashMap::new();
+        exp.insert(1, "a".to_owned());
+        exp.insert(2, "c".to_owned());
+        assert_eq!(attrs, exp);
+        assert!(parsed.remove("tags").unwrap().as_map::<u64, i64>().is_err());
+
+        let parsed = cmd.parse_args(args(&["test", "0"])).unwrap();
+        assert_eq!(
+            cmd.to_argv(&parsed).unwrap(),
+            args(&["test", "0", "TAGS", "0"])
+        );
+
+        let err = |a: &[&str]| format!("{:?}", cmd.parse_args(args(a)).unwrap_err());
+        assert_eq!(
+            err(&["test", "0", "tags", "2", "x", "1", "x", "2"]),
+            r#"String("tags has a duplicate key x")"#
+        );
+        assert_eq!(
+            err(&["test", "0", "tags", "3", "x", "1", "y", "2", "z", "3"]),
+            r#"String("tags can have at most 2 pairs")"#
+        );
+        assert_eq!(err(&["test", "1", "a"]), "WrongArity");
+        assert_eq!(err(&["test", "2", "1", "a", "2"]), "WrongArity");
+        assert_eq!(
+            err(&["test", "1", "a", "b"]),
+            r#"String("Couldn't parse as unsigned integer: a")"#
+        );
+    }
+
+    #[test]
+    fn set_test() {
+        let cmd = command! {
+            name: "test",
+            desc: "test desc",
+            args: [
+                ["members", "some members", ArgType::Arg, u64, Collection::Set, None],
+                ["tags", "some tags", ArgType::Kwarg, String, Collection::Set, Some(Box::new(Set::default())), max_len: 2, duplicates: Duplicates::Reject],
+            ],
+        };
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+        assert_eq!(cmd.arity(), -2);
+
+        let parsed = cmd
+            .parse_args(args(&["test", "4", "2", "1", "02", "3", "tags", "1", "x"]))
+            .unwrap();
+        assert_eq!(
+            cmd.to_argv(&parsed).unwrap(),
+            args(&["test", "3", "2", "1", "3", "TAGS", "1", "x"])
+        );
+
+        let mut parsed = parsed;
+        let members = parsed.remove("members").unwrap();
+        assert!(
+            (*members)
+                .as_any()
+                .downcast_ref::<Set>()
+                .unwrap()
+                .duplicates
+        );
+        assert_eq!(
+            members.as_hash_set::<u64>().unwrap(),
+            vec![1, 2, 3].into_iter().collect::<HashSet<u64>>()
+        );
+        let tags = parsed.remove("tags").unwrap().as_set().unwrap();
+        assert!(!tags.duplicates);
+        assert_eq!(tags.values.len(), 1);
+
+        let err = |a: &[&str]| format!("{:?}", cmd.parse_args(args(a)).unwrap_err());
+        assert_eq!(
+            err(&["test", "0", "tags", "2", "x", "x"]),
+            r#"String("tags has a duplicate value x")"#
+        );
+        assert_eq!(
+            err(&["test", "0", "tags", "3", "x", "y", "z"]),
+            r#"String("tags can have at most 2 values")"#
+        );
+        assert_eq!(err(&["test", "2", "1"]), "WrongArity");
+    }
+
+    #[test]
+    fn array_test() {
+        let cmd = command! {
+            name: "test",
+            desc: "test desc",
+            args: [
+                ["key", "a key", ArgType::Arg, String, Collection::Unit, None],
+                ["vector", "an embedding", ArgType::Arg, f64, Collection::Array(3), None],
+                ["pair", "a pair", ArgType::Kwarg, i64, Collection::Array(2), Some(Box::new(vec![Box::new(0_i64) as Box<dyn Value>, Box::new(1_i64)]))],
+            ],
+        };
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+        assert_eq!(cmd.arity(), -5);
+        assert_eq!(
+            cmd.to_string(),
+            "TEST key vector_1 ... vector_3 [PAIR pair_1 pair_2]"
+        );
+
+        let mut parsed = cmd
+            .parse_args(args(&["test", "k", "0.5", "1", "-2"]))
+            .unwrap();
+        assert_eq!(
+            cmd.to_argv(&parsed).unwrap(),
+            args(&["test", "k", "0.5", "1", "-2", "PAIR", "0", "1"])
+        );
+        assert_eq!(
+            parsed
+                .remove("vector")
+                .unwrap()
+                .as_array::<f64, 3>()
+                .unwrap(),
+            [0.5, 1.0, -2.0]
+        );
+        assert!(parsed.remove("pair").unwrap().as_array::<i64, 3>().is_err());
+
+        let parsed = cmd.parse_args(args(&["test", "k", "1", "2", "3", "pair", "4", "5"]));
+        assert_eq!(
+            parsed.unwrap().remove("pair").unwrap().as_i64vec().unwrap(),
+            vec![4, 5]
+        );
+
+        let err = |a: &[&str]| format!("{:?}", cmd.parse_args(args(a)).unwrap_err());
+        assert_eq!(
+            err(&["test", "k", "1", "2", "3", "pair", "4"]),
+            "WrongArity"
+        );
+        assert_eq!(
+            err(&["test", "k", "1", "2", "3", "4"]),
+            r#"String("Unexpected arg 4")"#
+        );
+
+        let mut cmd = Command::new("test", "test desc");
+        let res = cmd.try_add_arg(Arg::new(
+            "pair",
+            "a pair",
+            ArgType::Kwarg,
+            std::any::type_name::<i64>(),
+            Collection::Array(2),
+            Some(Box::new(vec![Box::new(0_i64) as Box<dyn Value>])),
+        ));
+        assert!(res.is_err());
+    }
 }
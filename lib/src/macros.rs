@@ -1,5 +1,52 @@
+/// Creates an `Arg`. Options follow the default as `option: value`, and
+/// are set with the `Arg` method of the same name, e.g. `max_len: 100`.
+/// The key type of a map is given like `Collection::Map(String)`. A default
+/// of a single value spelled out like `Some(Box::new(1))` has the declared
+/// data type.
 #[macro_export]
 macro_rules! argument {
+    ([
+        $arg:expr,
+        $desc:expr,
+        $argtype:expr,
+        $type:ty,
+        Collection::Map($key:ty),
+        $default:expr
+        $(, $option:ident: $value:expr)* $(,)*
+    ]) => {
+        $crate::argument!([
+            $arg,
+            $desc,
+            $argtype,
+            $type,
+            $crate::Collection::Map(std::any::type_name::<$key>()),
+            $default
+            $(, $option: $value)*
+        ])
+    };
+    ([
+        $arg:expr,
+        $desc:expr,
+        $argtype:expr,
+        $type:ty,
+        Collection::Unit,
+        Some(Box::new($default:expr))
+        $(, $option:ident: $value:expr)* $(,)*
+    ]) => {{
+        // the default gets the declared type, rather than e.g. `i32` for an
+        // integer literal, or fails to compile if it can't have it
+        let default: ::std::option::Option<::std::boxed::Box<$type>> =
+            ::std::option::Option::Some(::std::boxed::Box::new($default));
+        $crate::argument!([
+            $arg,
+            $desc,
+            $argtype,
+            $type,
+            $crate::Collection::Unit,
+            default.map(|d| d as ::std::boxed::Box<dyn $crate::Value>)
+            $(, $option: $value)*
+        ])
+    }};
     ([
         $arg:expr,
         $desc:expr,
@@ -7,6 +54,7 @@ macro_rules! argument {
         $type:ty,
         $kind:expr,
         $default:expr
+        $(, $option:ident: $value:expr)* $(,)*
     ]) => {
         $crate::Arg::new(
             $arg,
@@ -16,23 +64,217 @@ macro_rules! argument {
             $kind,
             $default,
         )
+        $(.$option($value))*
+    };
+}
+
+#[macro_export]
+macro_rules! example {
+    ([$invocation:expr, Ok]) => {
+        $crate::Example::new($invocation, $crate::Expect::Parses)
+    };
+    ([$invocation:expr, Err]) => {
+        $crate::Example::new($invocation, $crate::Expect::Fails)
+    };
+    ([$invocation:expr, Err($msg:expr)]) => {
+        $crate::Example::new($invocation, $crate::Expect::FailsWith($msg))
+    };
+    ($invocation:expr) => {
+        $crate::Example::new($invocation, $crate::Expect::Parses)
     };
 }
 
+/// Creates a `Command` from its args and optional examples. Examples are
+/// invocations that should parse, or `[invocation, Err]` and
+/// `[invocation, Err("error message")]` for invocations that shouldn't.
+/// `kwarg_policy: KwargPolicy::...` may follow the description, see
+/// `KwargPolicy`.
+///
+/// Definitions are checked at compile time: arg names must be unique, kwargs
+/// lowercase, optional positional args must be single values that can be
+/// left out with the `KwargPolicy`, and defaults spelled out as
+/// `Some(Box::new(..))` must have the declared type, down to the literal
+/// values of collections in a `vec!`. Options of args, like `max_len: 100`
+/// for `Vec` args, must be known and apply to the arg.
+///
+/// Other mistakes, e.g. a default returned by a helper fn that doesn't have
+/// the declared type, are only found when the command is built, which panics
+/// with the error of `Command::try_add_arg`. Commands built in a redis
+/// command handler, e.g. lazily in a `thread_local!`, would crash the server
+/// that way, so build them with `try_command!` there, which returns the error
+/// instead.
+///
+/// ```compile_fail
+/// # #[macro_use] extern crate redismodule_cmd;
+/// # use redismodule_cmd::{ArgType, Collection};
+/// let cmd = command! {
+///     name: "test",
+///     desc: "duplicate arg names",
+///     args: [
+///         ["foo", "bar", ArgType::Arg, String, Collection::Unit, None],
+///         ["foo", "baz", ArgType::Kwarg, String, Collection::Unit, None],
+///     ],
+/// };
+/// ```
+///
+/// ```compile_fail
+/// # #[macro_use] extern crate redismodule_cmd;
+/// # use redismodule_cmd::{ArgType, Collection};
+/// let cmd = command! {
+///     name: "test",
+///     desc: "default of the wrong type",
+///     args: [
+///         ["n", "a number", ArgType::Kwarg, u64, Collection::Unit, Some(Box::new(1_i64))],
+///     ],
+/// };
+/// ```
+///
+/// ```compile_fail
+/// # #[macro_use] extern crate redismodule_cmd;
+/// # use redismodule_cmd::{ArgType, Collection};
+/// let cmd = command! {
+///     name: "test",
+///     desc: "untyped literal default that can't have the type",
+///     args: [
+///         ["ratio", "a ratio", ArgType::Kwarg, f64, Collection::Unit, Some(Box::new(1))],
+///     ],
+/// };
+/// ```
+///
+/// ```compile_fail
+/// # #[macro_use] extern crate redismodule_cmd;
+/// # use redismodule_cmd::{ArgType, Collection, Value};
+/// let cmd = command! {
+///     name: "test",
+///     desc: "vec default with values of the wrong type",
+///     args: [
+///         ["vec1", "a vec", ArgType::Kwarg, u64, Collection::Vec, Some(Box::new(vec![Box::new(1_i64) as Box<dyn Value>]))],
+///     ],
+/// };
+/// ```
+///
+/// ```compile_fail
+/// # #[macro_use] extern crate redismodule_cmd;
+/// # use redismodule_cmd::{ArgType, Collection};
+/// let cmd = command! {
+///     name: "test",
+///     desc: "optional positional vec",
+///     args: [
+///         ["vec1", "a vec", ArgType::Arg, u64, Collection::Vec, Some(Box::new(Vec::<u64>::new()))],
+///     ],
+/// };
+/// ```
+///
+/// ```compile_fail
+/// # #[macro_use] extern crate redismodule_cmd;
+/// # use redismodule_cmd::{ArgType, Collection, KwargPolicy};
+/// let cmd = command! {
+///     name: "test",
+///     desc: "optional positional arg that can't be left out",
+///     kwarg_policy: KwargPolicy::Positional,
+///     args: [
+///         ["key", "a key", ArgType::Arg, String, Collection::Unit, Some(Box::new("k".to_owned()))],
+///         ["n", "a number", ArgType::Kwarg, u64, Collection::Unit, None],
+///     ],
+/// };
+/// ```
+///
+/// ```compile_fail
+/// # #[macro_use] extern crate redismodule_cmd;
+/// # use redismodule_cmd::{ArgType, Collection};
+/// let cmd = command! {
+///     name: "test",
+///     desc: "max_len of a single value",
+///     args: [
+///         ["n", "a number", ArgType::Kwarg, u64, Collection::Unit, None, max_len: 10],
+///     ],
+/// };
+/// ```
+///
+/// ```compile_fail
+/// # #[macro_use] extern crate redismodule_cmd;
+/// # use redismodule_cmd::{ArgType, Collection, Duplicates};
+/// let cmd = command! {
+///     name: "test",
+///     desc: "duplicates of a vec",
+///     args: [
+///         ["vec1", "a vec", ArgType::Kwarg, u64, Collection::Vec, None, duplicates: Duplicates::Reject],
+///     ],
+/// };
+/// ```
 #[macro_export]
 macro_rules! command {
+    (name: $name:expr, $($def:tt)*) => {
+        match $crate::try_command! { name: $name, $($def)* } {
+            Ok(cmd) => cmd,
+            Err(e) => panic!("Invalid command {}: {}", $name, e),
+        }
+    };
+}
+
+/// Creates a `Command` like `command!`, returning the error of the first arg
+/// that can't be added rather than panicking.
+///
+/// ```
+/// # #[macro_use] extern crate redismodule_cmd;
+/// # use redismodule_cmd::{ArgType, Collection, Value};
+/// fn limit() -> Option<Box<dyn Value>> {
+///     Some(Box::new(10_i64))
+/// }
+///
+/// let cmd = try_command! {
+///     name: "test",
+///     desc: "default of the wrong type",
+///     args: [
+///         ["limit", "a limit", ArgType::Kwarg, u64, Collection::Unit, limit()],
+///     ],
+/// };
+/// assert!(cmd.is_err());
+/// ```
+#[macro_export]
+macro_rules! try_command {
+    (
+        name: $name:expr,
+        desc: $desc:expr,
+        $(kwarg_policy: $policy:expr,)?
+        args: [
+            $($arg:tt),* $(,)*
+        ] $(,)*
+    ) => {
+        $crate::try_command! {
+            name: $name,
+            desc: $desc,
+            $(kwarg_policy: $policy,)?
+            args: [$($arg),*],
+            examples: [],
+        }
+    };
     (
         name: $name:expr,
         desc: $desc:expr,
+        $(kwarg_policy: $policy:expr,)?
         args: [
             $($arg:tt),* $(,)*
+        ],
+        examples: [
+            $($example:tt),* $(,)*
         ] $(,)*
     ) => {{
-        let mut _cmd = $crate::Command::new($name, $desc);
-        $(
-            let arg = $crate::argument!($arg);
-            _cmd.add_arg(arg);
-        )*
-        _cmd
+        $crate::validate_command! {
+            name: $name,
+            desc: $desc,
+            $(kwarg_policy: $policy,)?
+            args: [$($arg),*],
+        }
+        let mut _cmd = $crate::Command::new($name, $desc)$(.kwarg_policy($policy))?;
+        let args: ::std::vec::Vec<$crate::Arg> = vec![$($crate::argument!($arg)),*];
+        args.into_iter()
+            .try_for_each(|arg| _cmd.try_add_arg(arg))
+            .map(|_| {
+                $(
+                    _cmd.add_example($crate::example!($example));
+                )*
+                _cmd
+            })
     }};
 }
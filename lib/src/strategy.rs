@@ -0,0 +1,566 @@
+//! Proptest strategies generating args of a command, for property tests and
+//! fuzzing of commands. Valid args exercise every optional arg, kwarg and
+//! `Vec` length, invalid args are valid ones with a single mutation that
+//! `parse_args` must reject.
+
+use std::any::type_name;
+use std::collections::HashSet;
+
+use proptest::prelude::*;
+use proptest::sample::Index;
+use proptest::strategy::Union;
+
+use crate::types::{MAX_LATITUDE, MAX_LONGITUDE};
+use crate::{
+    parse_raw, raw_arg, Arg, Collection, Command, DistanceUnit, Duplicates, KwargPolicy, Latitude,
+    LexBound, Longitude, MemorySize, Milliseconds, ScoreBound, Seconds, StreamId,
+};
+
+/// Maximum length of generated `Vec` args.
+pub const MAX_VEC_LEN: usize = 5;
+
+/// Raw args, each flagged if it must parse as a data type other than
+/// `String`.
+type Tokens = Vec<(String, bool)>;
+
+/// Raw args of a command, grouped by arg. Kwargs are in random order.
+#[derive(Debug, Clone)]
+struct Parts {
+    name: String,
+    positional: Vec<Tokens>,
+    kwargs: Vec<Tokens>,
+}
+
+impl Parts {
+    fn tokens(self) -> Tokens {
+        let mut tokens = vec![(self.name, false)];
+        tokens.extend(self.positional.into_iter().flatten());
+        tokens.extend(self.kwargs.into_iter().flatten());
+        tokens
+    }
+
+    fn args(self) -> Vec<String> {
+        self.tokens().into_iter().map(|(t, _)| t).collect()
+    }
+}
+
+impl Command {
+    /// Generates args that `parse_args` accepts.
+    ///
+    /// Panics if a JSON arg has a schema made with `Schema::of`, as only
+    /// documents of a schema made with `Schema::arbitrary` can be generated.
+    pub fn valid_args(&self) -> BoxedStrategy<Vec<String>> {
+        let max_args = self.max_args.unwrap_or(usize::MAX);
+        self.parts(false)
+            .prop_map(Parts::args)
+            .prop_filter("too many args", move |args| args.len() <= max_args)
+            .boxed()
+    }
+
+    /// Generates args that `parse_args` rejects: a wrong command name, too
+    /// few args, a malformed value where a number or another data type is
+    /// expected, a trailing keyword without its value, or an extra arg after
+    /// all others. Panics like `valid_args`.
+    pub fn invalid_args(&self) -> BoxedStrategy<Vec<String>> {
+        let parts = self.parts(true);
+        let mut mutations: Vec<BoxedStrategy<Vec<String>>> = Vec::new();
+
+        let name = format!("{}_", self.name);
+        mutations.push(
+            parts
+                .clone()
+                .prop_map(move |mut p| {
+                    p.name = name.clone();
+                    p.args()
+                })
+                .boxed(),
+        );
+
+        let min = self.arity().unsigned_abs() as usize;
+        mutations.push(
+            (parts.clone(), 0..min)
+                .prop_map(|(p, len)| {
+                    let mut args = p.args();
+                    args.truncate(len);
+                    args
+                })
+                .boxed(),
+        );
+
+        let extra = self.fresh_token("extra");
+        mutations.push(
+            parts
+                .clone()
+                .prop_map(move |p| {
+                    let mut args = p.args();
+                    args.push(extra.clone());
+                    args
+                })
+                .boxed(),
+        );
+
+        if self.all_args().any(|a| match a.kind {
+            Collection::Unit | Collection::Array(_) => is_parsed(a.type_name),
+            // the count
+            _ => true,
+        }) {
+            let bad = self.fresh_token("not-a-number");
+            mutations.push(
+                (parts.clone(), any::<Index>())
+                    .prop_filter_map("no parsed arg", move |(p, index)| {
+                        let mut tokens = p.tokens();
+                        let parsed: Vec<usize> =
+                            (0..tokens.len()).filter(|i| tokens[*i].1).collect();
+                        if parsed.is_empty() {
+                            return None;
+                        }
+                        tokens[parsed[index.index(parsed.len())]].0 = bad.clone();
+                        Some(tokens.into_iter().map(|(t, _)| t).collect())
+                    })
+                    .boxed(),
+            );
+        }
+
+        if !self.keywords.is_empty() {
+            let keywords: Vec<String> = self.keywords.keys().map(|k| k.to_uppercase()).collect();
+            mutations.push(
+                (parts, proptest::sample::select(keywords))
+                    .prop_map(|(p, keyword)| {
+                        let mut args = p.args();
+                        args.push(keyword);
+                        args
+                    })
+                    .boxed(),
+            );
+        }
+
+        Union::new(mutations).boxed()
+    }
+
+    /// Generates the raw args of each arg. All optional positional args are
+    /// filled if `fill_optional`, else a random number of them.
+    fn parts(&self, fill_optional: bool) -> BoxedStrategy<Parts> {
+        let required: Vec<BoxedStrategy<Tokens>> = self
+            .required_args
+            .iter()
+            .map(|a| self.value_strategy(a))
+            .collect();
+        let optional: Vec<BoxedStrategy<Tokens>> = self
+            .optional_args
+            .iter()
+            .map(|a| self.value_strategy(a))
+            .collect();
+        let n_optional = self.optional_args.len();
+        let optional_len = if fill_optional {
+            Just(n_optional).boxed()
+        } else {
+            (0..=n_optional).boxed()
+        };
+
+        let kwargs: Vec<BoxedStrategy<Option<Tokens>>> = self
+            .keyword_groups()
+            .map(|(keyword, args)| {
+                let values: Vec<BoxedStrategy<Tokens>> =
+                    args.iter().map(|a| self.value_strategy(a)).collect();
+                let kwarg = (any::<bool>(), values).prop_map(move |(upper, v)| {
+                    let keyword = if upper {
+                        keyword.to_uppercase()
+                    } else {
+                        keyword.to_owned()
+                    };
+                    let mut tokens = vec![(keyword, false)];
+                    tokens.extend(v.into_iter().flatten());
+                    tokens
+                });
+                if args[0].default.is_none() {
+                    kwarg.prop_map(Some).boxed()
+                } else {
+                    proptest::option::of(kwarg).boxed()
+                }
+            })
+            .collect();
+        let kwargs = kwargs
+            .prop_map(|k| k.into_iter().flatten().collect::<Vec<Tokens>>())
+            .prop_shuffle();
+
+        let name = self.name.to_owned();
+        let policy = self.kwarg_policy;
+        (required, optional, optional_len, kwargs)
+            .prop_map(move |(required, mut optional, optional_len, kwargs)| {
+                // unless keywords are matched greedily, kwargs need all
+                // optional args or a terminator
+                match policy {
+                    _ if kwargs.is_empty() || optional_len == n_optional => {
+                        optional.truncate(optional_len)
+                    }
+                    KwargPolicy::Greedy => optional.truncate(optional_len),
+                    KwargPolicy::Positional => (),
+                    KwargPolicy::Terminator => {
+                        optional.truncate(optional_len);
+                        optional.push(vec![("--".to_owned(), false)]);
+                    }
+                }
+                Parts {
+                    name: name.clone(),
+                    positional: required.into_iter().chain(optional).collect(),
+                    kwargs,
+                }
+            })
+            .boxed()
+    }
+
+    /// Generates the raw args of the value of an arg, i.e. a single value,
+    /// the values of an array, or a count and that many values or pairs.
+    /// Values of sets and keys of maps are unique unless duplicates are
+    /// allowed.
+    fn value_strategy(&self, arg: &Arg) -> BoxedStrategy<Tokens> {
+        let value = self.arg_unit_strategy(arg);
+        let parsed = is_parsed(arg.type_name);
+        let max_len = arg
+            .max_len
+            .or(self.max_vec_len)
+            .unwrap_or(MAX_VEC_LEN)
+            .min(MAX_VEC_LEN);
+        let unique = arg.duplicates == Duplicates::Reject;
+        let cmd_name = self.name;
+        match arg.kind {
+            Collection::Unit => value.prop_map(move |v| vec![(v, parsed)]).boxed(),
+            Collection::Vec => proptest::collection::vec(value, 0..=max_len)
+                .prop_map(move |v| {
+                    let mut tokens = vec![(v.len().to_string(), true)];
+                    tokens.extend(v.into_iter().map(|v| (v, parsed)));
+                    tokens
+                })
+                .boxed(),
+            Collection::Set => {
+                let type_name = arg.type_name;
+                proptest::collection::vec(value, 0..=max_len)
+                    .prop_map(move |mut v| {
+                        if unique {
+                            dedup(&mut v, |v| v, type_name, cmd_name);
+                        }
+                        let mut tokens = vec![(v.len().to_string(), true)];
+                        tokens.extend(v.into_iter().map(|v| (v, parsed)));
+                        tokens
+                    })
+                    .boxed()
+            }
+            Collection::Array(len) => proptest::collection::vec(value, len)
+                .prop_map(move |v| v.into_iter().map(|v| (v, parsed)).collect())
+                .boxed(),
+            Collection::Map(key_type) => {
+                let key = self.unit_strategy(key_type);
+                let key_parsed = is_parsed(key_type);
+                proptest::collection::vec((key, value), 0..=max_len)
+                    .prop_map(move |mut pairs| {
+                        if unique {
+                            dedup(&mut pairs, |(k, _)| k, key_type, cmd_name);
+                        }
+                        let mut tokens = vec![(pairs.len().to_string(), true)];
+                        for (k, v) in pairs {
+                            tokens.push((k, key_parsed));
+                            tokens.push((v, parsed));
+                        }
+                        tokens
+                    })
+                    .boxed()
+            }
+        }
+    }
+
+    /// Generates a single raw value of an arg, i.e. a document of the type
+    /// of its schema for JSON args with one.
+    fn arg_unit_strategy(&self, arg: &Arg) -> BoxedStrategy<String> {
+        #[cfg(feature = "json")]
+        if let Some(schema) = &arg.schema {
+            return match schema.strategy() {
+                Some(strategy) => strategy,
+                None => panic!(
+                    "Can't generate documents of {} for {}, use Schema::arbitrary",
+                    schema.type_name, arg.arg
+                ),
+            };
+        }
+        self.unit_strategy(arg.type_name)
+    }
+
+    /// Generates a single raw value of a data type. Strings never match a
+    /// keyword, which would end optional positional args, nor `--`. JSON
+    /// documents are scalars, arrays or objects.
+    fn unit_strategy(&self, name: &str) -> BoxedStrategy<String> {
+        match name {
+            n if n == type_name::<u64>() => any::<u64>().prop_map(|v| v.to_string()).boxed(),
+            n if n == type_name::<i64>() => any::<i64>().prop_map(|v| v.to_string()).boxed(),
+            n if n == type_name::<f64>() => any::<f64>().prop_map(|v| v.to_string()).boxed(),
+            n if n == type_name::<Seconds>() || n == type_name::<Milliseconds>() => {
+                let units = vec!["", "ms", "s", "m", "h", "MS", "S"];
+                (1..1_000_000_u64, proptest::sample::select(units))
+                    .prop_map(|(v, unit)| format!("{}{}", v, unit))
+                    .boxed()
+            }
+            n if n == type_name::<ScoreBound>() => (
+                prop_oneof![
+                    any::<f64>().prop_map(|v| v.to_string()),
+                    Just("-inf".to_owned()),
+                    Just("+inf".to_owned()),
+                ],
+                any::<bool>(),
+            )
+                .prop_map(|(v, exclusive)| if exclusive { format!("({}", v) } else { v })
+                .boxed(),
+            n if n == type_name::<LexBound>() => prop_oneof![
+                Just("-".to_owned()),
+                Just("+".to_owned()),
+                "[\\[(]\\PC{0,8}",
+            ]
+            .boxed(),
+            n if n == type_name::<StreamId>() => prop_oneof![
+                "[-+*$>]",
+                any::<u64>().prop_map(|ms| ms.to_string()),
+                any::<(u64, u64)>().prop_map(|(ms, seq)| format!("{}-{}", ms, seq)),
+            ]
+            .boxed(),
+            n if n == type_name::<Longitude>() => (-MAX_LONGITUDE..=MAX_LONGITUDE)
+                .prop_map(|v| v.to_string())
+                .boxed(),
+            n if n == type_name::<Latitude>() => (-MAX_LATITUDE..=MAX_LATITUDE)
+                .prop_map(|v| v.to_string())
+                .boxed(),
+            n if n == type_name::<DistanceUnit>() => "(?i)(m|km|mi|ft)".boxed(),
+            #[cfg(feature = "json")]
+            n if n == type_name::<serde_json::Value>() => {
+                let scalar = prop_oneof![
+                    Just(serde_json::Value::Null),
+                    any::<bool>().prop_map(serde_json::Value::from),
+                    any::<i64>().prop_map(serde_json::Value::from),
+                    "\\PC{0,8}".prop_map(serde_json::Value::from),
+                ];
+                scalar
+                    .prop_recursive(2, 8, 4, |inner| {
+                        prop_oneof![
+                            proptest::collection::vec(inner.clone(), 0..4)
+                                .prop_map(serde_json::Value::from),
+                            proptest::collection::btree_map("[a-z]{1,4}", inner, 0..4)
+                                .prop_map(|m| serde_json::Value::Object(m.into_iter().collect())),
+                        ]
+                    })
+                    .prop_map(|v| v.to_string())
+                    .boxed()
+            }
+            n if n == type_name::<MemorySize>() => {
+                let units = vec!["", "b", "k", "kb", "m", "mb", "g", "gb", "MB", "Gb"];
+                (0..1_000_000_u64, proptest::sample::select(units))
+                    .prop_map(|(v, unit)| format!("{}{}", v, unit))
+                    .boxed()
+            }
+            _ => {
+                let keywords: Vec<&'static str> = self.keywords.keys().copied().collect();
+                "\\PC{0,8}"
+                    .prop_filter("keyword", move |s| {
+                        s != "--" && !keywords.contains(&s.to_lowercase().as_str())
+                    })
+                    .boxed()
+            }
+        }
+    }
+
+    /// All args, positional and keyword.
+    fn all_args(&self) -> impl Iterator<Item = &Arg> {
+        self.required_args
+            .iter()
+            .chain(self.optional_args.iter())
+            .chain(self.kwargs.values())
+    }
+
+    /// A raw arg starting with `base` that isn't a keyword.
+    fn fresh_token(&self, base: &str) -> String {
+        let mut token = base.to_owned();
+        while self.keywords.contains_key(token.as_str()) {
+            token.push('_');
+        }
+        token
+    }
+}
+
+/// Drops the items whose raw value parses to the same value as that of an
+/// earlier item, e.g. `1s` after `1000ms`.
+fn dedup<T>(items: &mut Vec<T>, raw: fn(&T) -> &String, type_name: &str, cmd_name: &str) {
+    let mut seen = HashSet::new();
+    items.retain(|item| match parse_raw(type_name, cmd_name, raw(item)) {
+        Ok(val) => seen.insert(raw_arg(&*val)),
+        Err(_) => true,
+    });
+}
+
+/// Whether raw values of a data type must parse as something other than a
+/// `String`, so that a word like `not-a-number` is rejected.
+fn is_parsed(name: &str) -> bool {
+    name != type_name::<String>()
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "json")]
+    use crate::json::Schema;
+    use crate::{
+        ArgType, Collection, Command, DistanceUnit, Duplicates, KwargPolicy, Latitude, LexBound,
+        Longitude, MemorySize, Pairs, ScoreBound, Seconds, Set, StreamId, Value,
+    };
+    use proptest::prelude::*;
+    use std::time::Duration;
+
+    fn cmd(kwarg_policy: KwargPolicy) -> Command {
+        if kwarg_policy == KwargPolicy::Positional {
+            return positional_cmd();
+        }
+        command! {
+            name: "hello.foo",
+            desc: "hello command example",
+            kwarg_policy: kwarg_policy,
+            args: [
+                ["input", "a string", ArgType::Arg, String, Collection::Unit, None],
+                ["vec2", "a vector of floats", ArgType::Arg, f64, Collection::Vec, None],
+                ["x", "a number", ArgType::Arg, i64, Collection::Unit, Some(Box::new(0_i64))],
+                ["optional", "a string", ArgType::Arg, String, Collection::Unit, Some(Box::new("baz".to_owned()))],
+                ["n", "some number", ArgType::Kwarg, u64, Collection::Unit, Some(Box::new(1_u64))],
+                ["s", "a string", ArgType::Kwarg, String, Collection::Unit, Some(Box::new("s".to_owned()))],
+                ["vec1", "a vector of ints", ArgType::Kwarg, i64, Collection::Vec, None],
+                ["min", "a score bound", ArgType::Arg, ScoreBound, Collection::Unit, None],
+                ["max", "a lex bound", ArgType::Arg, LexBound, Collection::Unit, None],
+                ["ids", "stream IDs", ArgType::Kwarg, StreamId, Collection::Vec, None],
+                ["lon", "a longitude", ArgType::Kwarg, Longitude, Collection::Unit, Some(Box::new(Longitude(0.0))), token: "fromlonlat"],
+                ["lat", "a latitude", ArgType::Kwarg, Latitude, Collection::Unit, Some(Box::new(Latitude(0.0))), token: "fromlonlat"],
+                ["unit", "a unit", ArgType::Kwarg, DistanceUnit, Collection::Unit, Some(Box::new(DistanceUnit::M))],
+                ["maxmemory", "a memory limit", ArgType::Kwarg, MemorySize, Collection::Unit, Some(Box::new(MemorySize(0)))],
+                ["ex", "a ttl", ArgType::Kwarg, Seconds, Collection::Unit, Some(Box::new(Seconds(Duration::from_secs(10))))],
+                ["limit_offset", "an offset", ArgType::Kwarg, u64, Collection::Unit, Some(Box::new(0_u64)), token: "limit"],
+                ["limit_count", "a count", ArgType::Kwarg, i64, Collection::Unit, Some(Box::new(-1_i64)), token: "limit"],
+                ["attrs", "some attributes", ArgType::Kwarg, u64, Collection::Map(String), Some(Box::new(Pairs::new()))],
+                ["ttls", "some ttls", ArgType::Kwarg, String, Collection::Map(Seconds), None, duplicates: Duplicates::Reject],
+                ["members", "some members", ArgType::Kwarg, MemorySize, Collection::Set, Some(Box::new(Set::default())), duplicates: Duplicates::Reject],
+                ["tags", "some tags", ArgType::Kwarg, String, Collection::Set, None, max_len: 3],
+                ["vector", "an embedding", ArgType::Arg, f64, Collection::Array(3), None],
+                ["names", "some names", ArgType::Kwarg, String, Collection::Array(2), Some(Box::new(vec![Box::new("a".to_owned()) as Box<dyn Value>, Box::new("b".to_owned())]))],
+            ],
+        }
+    }
+
+    /// Optional positional args can't be left out before required kwargs
+    /// with `KwargPolicy::Positional`, so all kwargs are optional.
+    fn positional_cmd() -> Command {
+        command! {
+            name: "hello.foo",
+            desc: "hello command example",
+            kwarg_policy: KwargPolicy::Positional,
+            args: [
+                ["input", "a string", ArgType::Arg, String, Collection::Unit, None],
+                ["x", "a number", ArgType::Arg, i64, Collection::Unit, Some(Box::new(0_i64))],
+                ["optional", "a string", ArgType::Arg, String, Collection::Unit, Some(Box::new("baz".to_owned()))],
+                ["n", "some number", ArgType::Kwarg, u64, Collection::Unit, Some(Box::new(1_u64))],
+                ["s", "a string", ArgType::Kwarg, String, Collection::Unit, Some(Box::new("s".to_owned()))],
+                ["unit", "a unit", ArgType::Kwarg, DistanceUnit, Collection::Unit, Some(Box::new(DistanceUnit::M))],
+                ["limit_offset", "an offset", ArgType::Kwarg, u64, Collection::Unit, Some(Box::new(0_u64)), token: "limit"],
+                ["limit_count", "a count", ArgType::Kwarg, i64, Collection::Unit, Some(Box::new(-1_i64)), token: "limit"],
+                ["attrs", "some attributes", ArgType::Kwarg, u64, Collection::Map(String), Some(Box::new(Pairs::new()))],
+                ["names", "some names", ArgType::Kwarg, String, Collection::Array(2), Some(Box::new(vec![Box::new("a".to_owned()) as Box<dyn Value>, Box::new("b".to_owned())]))],
+            ],
+        }
+    }
+
+    fn policy() -> impl Strategy<Value = KwargPolicy> {
+        prop_oneof![
+            Just(KwargPolicy::Greedy),
+            Just(KwargPolicy::Positional),
+            Just(KwargPolicy::Terminator),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn valid_args_test((p, args) in policy().prop_flat_map(|p| (Just(p), cmd(p).valid_args()))) {
+            prop_assert!(cmd(p).parse_args(args.clone()).is_ok(), "{:?}", args);
+        }
+
+        #[test]
+        fn to_argv_test((p, args) in policy().prop_flat_map(|p| (Just(p), cmd(p).valid_args()))) {
+            let cmd = cmd(p);
+            let parsed = cmd.parse_args(args).unwrap();
+            let argv = cmd.to_argv(&parsed).unwrap();
+            let reparsed = cmd.parse_args(argv.clone()).unwrap();
+            prop_assert_eq!(cmd.to_argv(&reparsed).unwrap(), argv);
+        }
+
+        #[test]
+        fn invalid_args_test((p, args) in policy().prop_flat_map(|p| (Just(p), cmd(p).invalid_args()))) {
+            prop_assert!(cmd(p).parse_args(args.clone()).is_err(), "{:?}", args);
+        }
+    }
+
+    #[cfg(feature = "json")]
+    #[derive(Debug, serde::Deserialize, serde::Serialize)]
+    struct Config {
+        name: String,
+        size: u64,
+    }
+
+    #[cfg(feature = "json")]
+    impl Arbitrary for Config {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Self>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            ("\\PC{0,8}", any::<u64>())
+                .prop_map(|(name, size)| Config { name, size })
+                .boxed()
+        }
+    }
+
+    #[cfg(feature = "json")]
+    fn json_cmd() -> Command {
+        command! {
+            name: "json.set",
+            desc: "json command example",
+            args: [
+                ["key", "a key", ArgType::Arg, String, Collection::Unit, None],
+                ["doc", "a document", ArgType::Arg, serde_json::Value, Collection::Unit, None],
+                ["meta", "metadata", ArgType::Kwarg, serde_json::Value, Collection::Unit, Some(Box::new(serde_json::Value::Null))],
+                ["configs", "some configs", ArgType::Kwarg, serde_json::Value, Collection::Vec, None, schema: Schema::arbitrary::<Config>()],
+            ],
+        }
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    #[should_panic(expected = "Can't generate documents of")]
+    fn schema_without_strategy_test() {
+        let cmd = command! {
+            name: "json.set",
+            desc: "json command example",
+            args: [
+                ["config", "a config", ArgType::Arg, serde_json::Value, Collection::Unit, None, schema: Schema::of::<Config>()],
+            ],
+        };
+        let _ = cmd.valid_args();
+    }
+
+    #[cfg(feature = "json")]
+    proptest! {
+        #[test]
+        fn json_test(args in json_cmd().valid_args()) {
+            let cmd = json_cmd();
+            let parsed = cmd.parse_args(args.clone()).unwrap();
+            let configs = cmd.parse_args(args).unwrap().remove("configs").unwrap();
+            for config in configs.as_vec().unwrap() {
+                prop_assert!(config.deserialize_json::<Config>().is_ok());
+            }
+            let argv = cmd.to_argv(&parsed).unwrap();
+            let reparsed = cmd.parse_args(argv.clone()).unwrap();
+            prop_assert_eq!(cmd.to_argv(&reparsed).unwrap(), argv);
+        }
+
+        #[test]
+        fn invalid_json_test(args in json_cmd().invalid_args()) {
+            prop_assert!(json_cmd().parse_args(args.clone()).is_err(), "{:?}", args);
+        }
+    }
+}
@@ -0,0 +1,818 @@
+//! Data types of args beyond `String`, `u64`, `i64` and `f64`, following the
+//! semantics of the redis commands taking such args.
+
+use std::any::type_name;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+use redis_module::RedisError;
+
+use crate::{Arg, Collection, Pairs, Set, Value};
+
+/// A data type parsed from a single raw arg, which `raw` formats back into
+/// one, e.g. in the client side builders of `codegen`.
+pub trait RawArg: Sized {
+    /// Parses a raw arg of the command `cmd`, which is named in errors.
+    fn parse(cmd: &str, raw: &str) -> Result<Self, RedisError>;
+
+    /// Formats the value as a raw arg, so that parsing it gives back the
+    /// same value.
+    fn raw(&self) -> String;
+}
+
+/// Parses a raw arg of one of the data types here, or `None` if the type
+/// named `name` isn't one of them.
+pub(crate) fn parse(
+    name: &str,
+    cmd: &str,
+    raw: &str,
+) -> Option<Result<Box<dyn Value>, RedisError>> {
+    let parse = match name {
+        n if n == type_name::<Seconds>() => parse_as::<Seconds>,
+        n if n == type_name::<Milliseconds>() => parse_as::<Milliseconds>,
+        n if n == type_name::<MemorySize>() => parse_as::<MemorySize>,
+        n if n == type_name::<ScoreBound>() => parse_as::<ScoreBound>,
+        n if n == type_name::<LexBound>() => parse_as::<LexBound>,
+        n if n == type_name::<StreamId>() => parse_as::<StreamId>,
+        n if n == type_name::<Longitude>() => parse_as::<Longitude>,
+        n if n == type_name::<Latitude>() => parse_as::<Latitude>,
+        n if n == type_name::<DistanceUnit>() => parse_as::<DistanceUnit>,
+        #[cfg(feature = "json")]
+        n if n == type_name::<serde_json::Value>() => parse_as::<serde_json::Value>,
+        _ => return None,
+    };
+    Some(parse(cmd, raw))
+}
+
+/// Formats a value of one of the data types here as a raw arg.
+pub(crate) fn raw(val: &dyn Value) -> Option<String> {
+    #[cfg(feature = "json")]
+    if let Some(raw) = raw_as::<serde_json::Value>(val) {
+        return Some(raw);
+    }
+    raw_as::<Seconds>(val)
+        .or_else(|| raw_as::<Milliseconds>(val))
+        .or_else(|| raw_as::<MemorySize>(val))
+        .or_else(|| raw_as::<ScoreBound>(val))
+        .or_else(|| raw_as::<LexBound>(val))
+        .or_else(|| raw_as::<StreamId>(val))
+        .or_else(|| raw_as::<Longitude>(val))
+        .or_else(|| raw_as::<Latitude>(val))
+        .or_else(|| raw_as::<DistanceUnit>(val))
+}
+
+fn parse_as<T: RawArg + Value>(cmd: &str, raw: &str) -> Result<Box<dyn Value>, RedisError> {
+    Ok(Box::new(T::parse(cmd, raw)?))
+}
+
+fn raw_as<T: RawArg + 'static>(val: &dyn Value) -> Option<String> {
+    val.as_any().downcast_ref::<T>().map(|v| v.raw())
+}
+
+/// A positive duration given in seconds, like the `EX` of `SET`. Other
+/// units can be given with a suffix: `500ms`, `10s`, `5m` or `1h`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Seconds(pub Duration);
+
+/// A positive duration given in milliseconds, like the `PX` of `SET`.
+/// Other units can be given with a suffix: `500ms`, `10s`, `5m` or `1h`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Milliseconds(pub Duration);
+
+impl RawArg for Seconds {
+    fn parse(cmd: &str, raw: &str) -> Result<Self, RedisError> {
+        parse_duration(cmd, raw, 1000).map(Seconds)
+    }
+
+    fn raw(&self) -> String {
+        if self.0.subsec_millis() == 0 {
+            self.0.as_secs().to_string()
+        } else {
+            format!("{}ms", self.0.as_millis())
+        }
+    }
+}
+
+impl RawArg for Milliseconds {
+    fn parse(cmd: &str, raw: &str) -> Result<Self, RedisError> {
+        parse_duration(cmd, raw, 1).map(Milliseconds)
+    }
+
+    fn raw(&self) -> String {
+        self.0.as_millis().to_string()
+    }
+}
+
+/// Parses a whole number of `unit_ms` milliseconds, or of the unit given
+/// by a suffix. Like redis, durations must be positive and fit in an `i64`
+/// of milliseconds.
+fn parse_duration(cmd: &str, raw: &str, unit_ms: u64) -> Result<Duration, RedisError> {
+    let invalid = || RedisError::String(format!("invalid expire time in '{}' command", cmd));
+    let not_an_integer = || RedisError::Str("value is not an integer or out of range");
+
+    let digits = raw.strip_prefix('-').unwrap_or(raw);
+    let end = digits
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(digits.len());
+    if end == 0 {
+        return Err(not_an_integer());
+    }
+    let unit_ms = match digits[end..].to_lowercase().as_str() {
+        "" => unit_ms,
+        "ms" => 1,
+        "s" => 1000,
+        "m" => 60 * 1000,
+        "h" => 60 * 60 * 1000,
+        _ => return Err(not_an_integer()),
+    };
+    if digits.len() != raw.len() {
+        return Err(invalid());
+    }
+
+    let ms = digits[..end]
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(unit_ms))
+        .filter(|ms| *ms > 0 && *ms <= i64::MAX as u64)
+        .ok_or_else(invalid)?;
+    Ok(Duration::from_millis(ms))
+}
+
+/// An amount of memory in bytes, given like in `redis.conf`: a number of
+/// bytes, optionally with a case-insensitive suffix. `k`, `m` and `g` are
+/// powers of 1000, `kb`, `mb` and `gb` powers of 1024.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MemorySize(pub u64);
+
+/// Suffixes of memory sizes, with the number of bytes they stand for.
+const MEMORY_UNITS: [(&str, u64); 7] = [
+    ("b", 1),
+    ("k", 1000),
+    ("kb", 1024),
+    ("m", 1000 * 1000),
+    ("mb", 1024 * 1024),
+    ("g", 1000 * 1000 * 1000),
+    ("gb", 1024 * 1024 * 1024),
+];
+
+impl RawArg for MemorySize {
+    fn parse(_cmd: &str, raw: &str) -> Result<Self, RedisError> {
+        let invalid = || RedisError::Str("argument must be a memory value");
+
+        let end = raw.find(|c: char| !c.is_ascii_digit()).unwrap_or(raw.len());
+        if end == 0 {
+            return Err(invalid());
+        }
+        let suffix = raw[end..].to_lowercase();
+        let unit = match MEMORY_UNITS.iter().find(|(s, _)| *s == suffix) {
+            Some((_, unit)) => *unit,
+            None if suffix.is_empty() => 1,
+            None => return Err(invalid()),
+        };
+
+        raw[..end]
+            .parse::<u64>()
+            .ok()
+            .and_then(|n| n.checked_mul(unit))
+            .map(MemorySize)
+            .ok_or_else(invalid)
+    }
+
+    /// Formats the size with the largest power of 1024 it is a multiple
+    /// of, e.g. `64mb`.
+    fn raw(&self) -> String {
+        let (suffix, shift) = match self.0.trailing_zeros() {
+            64 => ("", 0),
+            z if z >= 30 => ("gb", 30),
+            z if z >= 20 => ("mb", 20),
+            z if z >= 10 => ("kb", 10),
+            _ => ("", 0),
+        };
+        format!("{}{}", self.0 >> shift, suffix)
+    }
+}
+
+/// A bound of a score range, like the `min` and `max` of `ZRANGEBYSCORE`:
+/// a float, `-inf` or `+inf`, exclusive if prefixed with `(`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreBound {
+    pub value: f64,
+    pub exclusive: bool,
+}
+
+impl ScoreBound {
+    /// Whether `score` is within a range with this bound as its minimum.
+    pub fn gte_min(&self, score: f64) -> bool {
+        if self.exclusive {
+            score > self.value
+        } else {
+            score >= self.value
+        }
+    }
+
+    /// Whether `score` is within a range with this bound as its maximum.
+    pub fn lte_max(&self, score: f64) -> bool {
+        if self.exclusive {
+            score < self.value
+        } else {
+            score <= self.value
+        }
+    }
+}
+
+impl RawArg for ScoreBound {
+    fn parse(_cmd: &str, raw: &str) -> Result<Self, RedisError> {
+        let (value, exclusive) = match raw.strip_prefix('(') {
+            Some(value) => (value, true),
+            None => (raw, false),
+        };
+        match value.parse::<f64>() {
+            Ok(value) if !value.is_nan() => Ok(ScoreBound { value, exclusive }),
+            _ => Err(RedisError::Str("min or max is not a float")),
+        }
+    }
+
+    fn raw(&self) -> String {
+        if self.exclusive {
+            format!("({}", self.value)
+        } else {
+            self.value.to_string()
+        }
+    }
+}
+
+/// A bound of a lexicographical range, like the `min` and `max` of
+/// `ZRANGEBYLEX`: `-`, `+`, or a string prefixed with `[` if inclusive or
+/// `(` if exclusive. Strings are compared as bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LexBound {
+    /// `-`, below any string.
+    NegInfinity,
+    /// `+`, above any string.
+    PosInfinity,
+    Inclusive(Vec<u8>),
+    Exclusive(Vec<u8>),
+}
+
+impl LexBound {
+    /// Whether `s` is within a range with this bound as its minimum.
+    pub fn gte_min(&self, s: &[u8]) -> bool {
+        match self {
+            LexBound::NegInfinity => true,
+            LexBound::PosInfinity => false,
+            LexBound::Inclusive(min) => s >= min.as_slice(),
+            LexBound::Exclusive(min) => s > min.as_slice(),
+        }
+    }
+
+    /// Whether `s` is within a range with this bound as its maximum.
+    pub fn lte_max(&self, s: &[u8]) -> bool {
+        match self {
+            LexBound::NegInfinity => false,
+            LexBound::PosInfinity => true,
+            LexBound::Inclusive(max) => s <= max.as_slice(),
+            LexBound::Exclusive(max) => s < max.as_slice(),
+        }
+    }
+}
+
+impl RawArg for LexBound {
+    fn parse(_cmd: &str, raw: &str) -> Result<Self, RedisError> {
+        let bytes = raw.as_bytes();
+        match bytes.first() {
+            Some(b'-') if bytes.len() == 1 => Ok(LexBound::NegInfinity),
+            Some(b'+') if bytes.len() == 1 => Ok(LexBound::PosInfinity),
+            Some(b'[') => Ok(LexBound::Inclusive(bytes[1..].to_vec())),
+            Some(b'(') => Ok(LexBound::Exclusive(bytes[1..].to_vec())),
+            _ => Err(RedisError::Str("min or max not valid string range item")),
+        }
+    }
+
+    fn raw(&self) -> String {
+        match self {
+            LexBound::NegInfinity => "-".to_owned(),
+            LexBound::PosInfinity => "+".to_owned(),
+            LexBound::Inclusive(s) => format!("[{}", String::from_utf8_lossy(s)),
+            LexBound::Exclusive(s) => format!("({}", String::from_utf8_lossy(s)),
+        }
+    }
+}
+
+/// An ID of a stream entry, `ms-seq`, or `ms` only with the sequence left
+/// to the command, or one of the special IDs of stream commands.
+///
+/// IDs are ordered by their position in a stream: `-` before all entry IDs,
+/// `+` after them, with `ms` only before the IDs of the same `ms`. `*`, `$`
+/// and `>` don't have a position and are ordered after all others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum StreamId {
+    /// `-`, the smallest ID.
+    Min,
+    /// `ms-seq`, or `ms` with `seq` missing.
+    Id { ms: u64, seq: Option<u64> },
+    /// `+`, the largest ID.
+    Max,
+    /// `*`, an ID to be generated, e.g. by `XADD`.
+    Auto,
+    /// `$`, the last ID of the stream.
+    Last,
+    /// `>`, the entries never delivered to a consumer group.
+    Undelivered,
+}
+
+impl StreamId {
+    /// The ID starting a range, `ms-0` if the sequence is missing. `None`
+    /// for `*`, `$` and `>`.
+    pub fn start(&self) -> Option<(u64, u64)> {
+        match *self {
+            StreamId::Min => Some((0, 0)),
+            StreamId::Id { ms, seq } => Some((ms, seq.unwrap_or(0))),
+            StreamId::Max => Some((u64::MAX, u64::MAX)),
+            _ => None,
+        }
+    }
+
+    /// The ID ending a range, with the largest sequence if it is missing.
+    /// `None` for `*`, `$` and `>`.
+    pub fn end(&self) -> Option<(u64, u64)> {
+        match *self {
+            StreamId::Id { ms, seq } => Some((ms, seq.unwrap_or(u64::MAX))),
+            _ => self.start(),
+        }
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StreamId::Min => write!(f, "-"),
+            StreamId::Id { ms, seq: Some(seq) } => write!(f, "{}-{}", ms, seq),
+            StreamId::Id { ms, seq: None } => write!(f, "{}", ms),
+            StreamId::Max => write!(f, "+"),
+            StreamId::Auto => write!(f, "*"),
+            StreamId::Last => write!(f, "$"),
+            StreamId::Undelivered => write!(f, ">"),
+        }
+    }
+}
+
+impl RawArg for StreamId {
+    fn parse(_cmd: &str, raw: &str) -> Result<Self, RedisError> {
+        let invalid = || RedisError::Str("Invalid stream ID specified as stream command argument");
+        // like u64 parsing, but without a leading +
+        let number = |s: &str| match s.bytes().all(|b| b.is_ascii_digit()) {
+            true => s.parse::<u64>().map_err(|_| invalid()),
+            false => Err(invalid()),
+        };
+
+        match raw {
+            "-" => Ok(StreamId::Min),
+            "+" => Ok(StreamId::Max),
+            "*" => Ok(StreamId::Auto),
+            "$" => Ok(StreamId::Last),
+            ">" => Ok(StreamId::Undelivered),
+            _ => {
+                let (ms, seq) = match raw.find('-') {
+                    Some(i) => (&raw[..i], Some(number(&raw[i + 1..])?)),
+                    None => (raw, None),
+                };
+                Ok(StreamId::Id {
+                    ms: number(ms)?,
+                    seq,
+                })
+            }
+        }
+    }
+
+    fn raw(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// Largest absolute longitude of redis GEO commands.
+pub const MAX_LONGITUDE: f64 = 180.0;
+
+/// Largest absolute latitude of redis GEO commands, beyond which the
+/// projection they use is undefined.
+pub const MAX_LATITUDE: f64 = 85.05112878;
+
+/// A longitude in degrees, like in `GEOADD`. An arg of this type followed
+/// by a `Latitude` arg is checked as a pair, see `check_coordinates`.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Longitude(pub f64);
+
+/// A latitude in degrees, like in `GEOADD`.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Latitude(pub f64);
+
+impl RawArg for Longitude {
+    fn parse(_cmd: &str, raw: &str) -> Result<Self, RedisError> {
+        parse_degrees(raw).map(Longitude)
+    }
+
+    fn raw(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+impl RawArg for Latitude {
+    fn parse(_cmd: &str, raw: &str) -> Result<Self, RedisError> {
+        parse_degrees(raw).map(Latitude)
+    }
+
+    fn raw(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+fn parse_degrees(raw: &str) -> Result<f64, RedisError> {
+    match raw.parse::<f64>() {
+        Ok(d) if !d.is_nan() => Ok(d),
+        _ => Err(RedisError::Str("value is not a valid float")),
+    }
+}
+
+/// Checks the ranges of the coordinates among `args`, which are positional
+/// args or kwargs sharing a keyword, in the order they are given. Like
+/// `GEOADD`, a longitude followed by a latitude is reported as a pair. Other
+/// coordinates, e.g. the values of a `Vec`, are reported on their own as an
+/// invalid longitude or latitude.
+pub(crate) fn check_coordinates(
+    args: &[&Arg],
+    parsed: &HashMap<&'static str, Box<dyn Value>>,
+) -> Result<(), RedisError> {
+    // `Vec`s of coordinates have no degrees of their own
+    let unit = |arg: &Arg| parsed.get(arg.arg).and_then(|v| degrees(&**v));
+    let is = |arg: &Arg, name: &str| arg.type_name == name;
+
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i];
+        i += 1;
+        if is(arg, type_name::<Longitude>()) {
+            let pair = args.get(i).filter(|a| is(a, type_name::<Latitude>()));
+            if let (Some(lon), Some(lat)) = (unit(arg), pair.and_then(|a| unit(a))) {
+                if lon.abs() > MAX_LONGITUDE || lat.abs() > MAX_LATITUDE {
+                    return Err(RedisError::String(format!(
+                        "invalid longitude,latitude pair {:.6},{:.6}",
+                        lon, lat
+                    )));
+                }
+                i += 1;
+                continue;
+            }
+        }
+        check_degrees(arg, parsed)?;
+    }
+    Ok(())
+}
+
+/// Checks the ranges of the coordinates of an arg that isn't part of a pair,
+/// including the values of collections and the keys of maps.
+fn check_degrees(
+    arg: &Arg,
+    parsed: &HashMap<&'static str, Box<dyn Value>>,
+) -> Result<(), RedisError> {
+    let is_coordinate = |t: &str| t == type_name::<Longitude>() || t == type_name::<Latitude>();
+    let key_type = match arg.kind {
+        Collection::Map(key_type) => key_type,
+        _ => "",
+    };
+    let val = match parsed.get(arg.arg) {
+        Some(val) if is_coordinate(arg.type_name) || is_coordinate(key_type) => &**val,
+        _ => return Ok(()),
+    };
+
+    let any = val.as_any();
+    let vals: Vec<&dyn Value> = if let Some(v) = any.downcast_ref::<Vec<Box<dyn Value>>>() {
+        v.iter().map(|v| &**v).collect()
+    } else if let Some(s) = any.downcast_ref::<Set>() {
+        s.values.iter().map(|v| &**v).collect()
+    } else if let Some(p) = any.downcast_ref::<Pairs>() {
+        p.iter().flat_map(|(k, v)| vec![&**k, &**v]).collect()
+    } else {
+        vec![val]
+    };
+    for v in vals {
+        let (name, max) = if v.as_any().is::<Longitude>() {
+            ("longitude", MAX_LONGITUDE)
+        } else {
+            ("latitude", MAX_LATITUDE)
+        };
+        if let Some(d) = degrees(v).filter(|d| d.abs() > max) {
+            return Err(RedisError::String(format!("invalid {} {:.6}", name, d)));
+        }
+    }
+    Ok(())
+}
+
+/// Degrees of a parsed longitude or latitude.
+fn degrees(val: &dyn Value) -> Option<f64> {
+    let any = val.as_any();
+    any.downcast_ref::<Longitude>()
+        .map(|l| l.0)
+        .or_else(|| any.downcast_ref::<Latitude>().map(|l| l.0))
+}
+
+/// A unit of distance of GEO commands, given case-insensitively.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DistanceUnit {
+    /// `m`, meters.
+    M,
+    /// `km`, kilometers.
+    Km,
+    /// `mi`, miles.
+    Mi,
+    /// `ft`, feet.
+    Ft,
+}
+
+impl DistanceUnit {
+    /// Number of meters in one unit.
+    pub fn meters(&self) -> f64 {
+        match self {
+            DistanceUnit::M => 1.0,
+            DistanceUnit::Km => 1000.0,
+            DistanceUnit::Mi => 1609.34,
+            DistanceUnit::Ft => 0.3048,
+        }
+    }
+}
+
+impl RawArg for DistanceUnit {
+    fn parse(_cmd: &str, raw: &str) -> Result<Self, RedisError> {
+        match raw.to_lowercase().as_str() {
+            "m" => Ok(DistanceUnit::M),
+            "km" => Ok(DistanceUnit::Km),
+            "mi" => Ok(DistanceUnit::Mi),
+            "ft" => Ok(DistanceUnit::Ft),
+            _ => Err(RedisError::Str(
+                "unsupported unit provided. please use M, KM, FT, MI",
+            )),
+        }
+    }
+
+    fn raw(&self) -> String {
+        match self {
+            DistanceUnit::M => "m",
+            DistanceUnit::Km => "km",
+            DistanceUnit::Mi => "mi",
+            DistanceUnit::Ft => "ft",
+        }
+        .to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        DistanceUnit, Latitude, LexBound, Longitude, MemorySize, Milliseconds, RawArg, ScoreBound,
+        Seconds, StreamId,
+    };
+    use std::time::Duration;
+
+    #[test]
+    fn duration_test() {
+        let secs = |raw| Seconds::parse("set", raw).map(|s| s.0);
+        assert_eq!(secs("10").unwrap(), Duration::from_secs(10));
+        assert_eq!(secs("500ms").unwrap(), Duration::from_millis(500));
+        assert_eq!(secs("10S").unwrap(), Duration::from_secs(10));
+        assert_eq!(secs("5m").unwrap(), Duration::from_secs(300));
+        assert_eq!(secs("1h").unwrap(), Duration::from_secs(3600));
+
+        let ms = |raw| Milliseconds::parse("set", raw).map(|s| s.0);
+        assert_eq!(ms("10").unwrap(), Duration::from_millis(10));
+        assert_eq!(ms("10s").unwrap(), Duration::from_secs(10));
+
+        let invalid = "invalid expire time in 'set' command";
+        for raw in &[
+            "0",
+            "0ms",
+            "-1",
+            "-10s",
+            "9223372036854775808",
+            "9223372036854776s",
+        ] {
+            let err = format!("{:?}", secs(raw).unwrap_err());
+            assert!(err.contains(invalid), "{}: {}", raw, err);
+        }
+        assert!(ms("9223372036854775807").is_ok());
+
+        let not_an_integer = "value is not an integer or out of range";
+        for raw in &["", "s", "ten", "10x", "1.5s", " 10", "--5", "-"] {
+            let err = format!("{:?}", secs(raw).unwrap_err());
+            assert!(err.contains(not_an_integer), "{}: {}", raw, err);
+        }
+    }
+
+    #[test]
+    fn raw_test() {
+        assert_eq!(Seconds(Duration::from_secs(10)).raw(), "10");
+        assert_eq!(Seconds(Duration::from_millis(1500)).raw(), "1500ms");
+        assert_eq!(Milliseconds(Duration::from_secs(10)).raw(), "10000");
+
+        let max = Milliseconds::parse("set", "9223372036854775807").unwrap();
+        assert_eq!(Milliseconds::parse("set", &max.raw()).unwrap(), max);
+    }
+
+    #[test]
+    fn memory_size_test() {
+        let size = |raw| MemorySize::parse("config", raw).map(|s| s.0);
+        assert_eq!(size("100").unwrap(), 100);
+        assert_eq!(size("100b").unwrap(), 100);
+        assert_eq!(size("1k").unwrap(), 1000);
+        assert_eq!(size("1KB").unwrap(), 1024);
+        assert_eq!(size("64mb").unwrap(), 64 * 1024 * 1024);
+        assert_eq!(size("2M").unwrap(), 2_000_000);
+        assert_eq!(size("1gb").unwrap(), 1 << 30);
+        assert_eq!(size("3g").unwrap(), 3_000_000_000);
+        assert_eq!(size("18446744073709551615").unwrap(), u64::MAX);
+
+        for raw in &[
+            "",
+            "mb",
+            "-1",
+            "1.5gb",
+            "1tb",
+            "18446744073709551616",
+            "17179869184gb",
+        ] {
+            let err = format!("{:?}", size(raw).unwrap_err());
+            assert!(
+                err.contains("argument must be a memory value"),
+                "{}: {}",
+                raw,
+                err
+            );
+        }
+
+        assert_eq!(MemorySize(0).raw(), "0");
+        assert_eq!(MemorySize(1000).raw(), "1000");
+        assert_eq!(MemorySize(2048).raw(), "2kb");
+        assert_eq!(MemorySize(64 * 1024 * 1024).raw(), "64mb");
+        assert_eq!(MemorySize(1 << 40).raw(), "1024gb");
+    }
+
+    #[test]
+    fn score_bound_test() {
+        let bound = |raw: &str| ScoreBound::parse("zrangebyscore", raw);
+        let inclusive = |value| ScoreBound {
+            value,
+            exclusive: false,
+        };
+        let exclusive = |value| ScoreBound {
+            value,
+            exclusive: true,
+        };
+        assert_eq!(bound("1.5").unwrap(), inclusive(1.5));
+        assert_eq!(bound("(1.5").unwrap(), exclusive(1.5));
+        assert_eq!(bound("-inf").unwrap(), inclusive(f64::NEG_INFINITY));
+        assert_eq!(bound("+inf").unwrap(), inclusive(f64::INFINITY));
+        assert_eq!(bound("(-inf").unwrap(), exclusive(f64::NEG_INFINITY));
+        assert_eq!(bound("-2e3").unwrap(), inclusive(-2000.0));
+
+        for raw in &["", "(", "((1", "[1", "1.5)", "nan", "(nan", "one"] {
+            let err = format!("{:?}", bound(raw).unwrap_err());
+            assert!(
+                err.contains("min or max is not a float"),
+                "{}: {}",
+                raw,
+                err
+            );
+        }
+
+        assert!(inclusive(1.0).gte_min(1.0));
+        assert!(!exclusive(1.0).gte_min(1.0));
+        assert!(exclusive(1.0).gte_min(1.5));
+        assert!(inclusive(1.0).lte_max(1.0));
+        assert!(!exclusive(1.0).lte_max(1.0));
+        assert!(inclusive(f64::INFINITY).lte_max(f64::MAX));
+
+        for b in &[
+            inclusive(1.5),
+            exclusive(-2.0),
+            exclusive(f64::NEG_INFINITY),
+        ] {
+            assert_eq!(bound(&b.raw()).unwrap(), *b);
+        }
+    }
+
+    #[test]
+    fn lex_bound_test() {
+        let bound = |raw: &str| LexBound::parse("zrangebylex", raw);
+        assert_eq!(bound("-").unwrap(), LexBound::NegInfinity);
+        assert_eq!(bound("+").unwrap(), LexBound::PosInfinity);
+        assert_eq!(bound("[a").unwrap(), LexBound::Inclusive(b"a".to_vec()));
+        assert_eq!(bound("(b").unwrap(), LexBound::Exclusive(b"b".to_vec()));
+        assert_eq!(bound("[").unwrap(), LexBound::Inclusive(Vec::new()));
+        assert_eq!(bound("(-").unwrap(), LexBound::Exclusive(b"-".to_vec()));
+
+        for raw in &["", "a", "--", "+a", "]a", " [a"] {
+            let err = format!("{:?}", bound(raw).unwrap_err());
+            assert!(
+                err.contains("min or max not valid string range item"),
+                "{}: {}",
+                raw,
+                err
+            );
+        }
+
+        assert!(LexBound::NegInfinity.gte_min(b""));
+        assert!(!LexBound::NegInfinity.lte_max(b""));
+        assert!(LexBound::Inclusive(b"a".to_vec()).gte_min(b"a"));
+        assert!(!LexBound::Exclusive(b"a".to_vec()).gte_min(b"a"));
+        assert!(LexBound::Exclusive(b"a".to_vec()).gte_min(b"ab"));
+        assert!(LexBound::Exclusive(b"b".to_vec()).lte_max(b"ab"));
+        assert!(!LexBound::Inclusive(b"b".to_vec()).lte_max(b"ba"));
+
+        for raw in &["-", "+", "[a", "(", "(\u{e9}t\u{e9}"] {
+            assert_eq!(bound(raw).unwrap().raw(), *raw);
+        }
+    }
+
+    #[test]
+    fn stream_id_test() {
+        let id = |raw: &str| StreamId::parse("xrange", raw);
+        let full = |ms, seq| StreamId::Id { ms, seq: Some(seq) };
+        assert_eq!(id("1526919030474-55").unwrap(), full(1526919030474, 55));
+        assert_eq!(
+            id("1526919030474").unwrap(),
+            StreamId::Id {
+                ms: 1526919030474,
+                seq: None
+            }
+        );
+        assert_eq!(id("0-0").unwrap(), full(0, 0));
+        assert_eq!(id("-").unwrap(), StreamId::Min);
+        assert_eq!(id("+").unwrap(), StreamId::Max);
+        assert_eq!(id("*").unwrap(), StreamId::Auto);
+        assert_eq!(id("$").unwrap(), StreamId::Last);
+        assert_eq!(id(">").unwrap(), StreamId::Undelivered);
+
+        for raw in &[
+            "",
+            "1-",
+            "-1",
+            "1-2-3",
+            "+1",
+            "1-+2",
+            "a-1",
+            "18446744073709551616",
+            "1-*",
+        ] {
+            let err = format!("{:?}", id(raw).unwrap_err());
+            assert!(
+                err.contains("Invalid stream ID specified as stream command argument"),
+                "{}: {}",
+                raw,
+                err
+            );
+        }
+
+        let ms_only = StreamId::Id { ms: 5, seq: None };
+        assert!(StreamId::Min < full(0, 0));
+        assert!(full(0, 0) < full(0, 1));
+        assert!(full(1, 0) < full(2, 0));
+        assert!(ms_only < full(5, 0));
+        assert!(full(u64::MAX, u64::MAX) < StreamId::Max);
+        assert_eq!(ms_only.start(), Some((5, 0)));
+        assert_eq!(ms_only.end(), Some((5, u64::MAX)));
+        assert_eq!(StreamId::Last.start(), None);
+
+        for raw in &["1-2", "3", "-", "+", "*", "$", ">"] {
+            assert_eq!(id(raw).unwrap().raw(), *raw);
+        }
+    }
+
+    #[test]
+    fn coordinates_test() {
+        assert_eq!(
+            Longitude::parse("geoadd", "13.361389").unwrap(),
+            Longitude(13.361389)
+        );
+        assert_eq!(
+            Latitude::parse("geoadd", "-38.115556").unwrap(),
+            Latitude(-38.115556)
+        );
+        for raw in &["", "east", "nan", "1,2"] {
+            let err = format!("{:?}", Longitude::parse("geoadd", raw).unwrap_err());
+            assert!(
+                err.contains("value is not a valid float"),
+                "{}: {}",
+                raw,
+                err
+            );
+        }
+        assert_eq!(Longitude(-180.0).raw(), "-180");
+
+        assert_eq!(
+            DistanceUnit::parse("geosearch", "KM").unwrap(),
+            DistanceUnit::Km
+        );
+        assert_eq!(DistanceUnit::parse("geosearch", "ft").unwrap().raw(), "ft");
+        assert_eq!(DistanceUnit::Mi.meters(), 1609.34);
+        let err = format!("{:?}", DistanceUnit::parse("geosearch", "yd").unwrap_err());
+        assert!(err.contains("unsupported unit provided. please use M, KM, FT, MI"));
+    }
+}
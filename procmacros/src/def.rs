@@ -0,0 +1,303 @@
+//! Syntax of the `command!` macro body.
+
+use proc_macro2::Span;
+use quote::ToTokens;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{bracketed, Expr, Ident, Token, Type};
+
+pub struct CommandDef {
+    pub name: Expr,
+    pub desc: Expr,
+    pub kwarg_policy: Option<Expr>,
+    pub args: Vec<ArgDef>,
+    pub examples: Vec<ExampleDef>,
+}
+
+pub struct ArgDef {
+    pub span: Span,
+    pub name: Expr,
+    pub desc: Expr,
+    pub arg_type: Expr,
+    pub data_type: Type,
+    pub kind: Expr,
+    pub default: Expr,
+    pub options: Vec<(Ident, Expr)>,
+}
+
+impl Parse for CommandDef {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        parse_key(input, "name")?;
+        let name: Expr = input.parse()?;
+        input.parse::<Token![,]>()?;
+
+        parse_key(input, "desc")?;
+        let desc: Expr = input.parse()?;
+        input.parse::<Token![,]>()?;
+
+        let mut kwarg_policy = None;
+        if input
+            .fork()
+            .parse::<Ident>()
+            .is_ok_and(|i| i == "kwarg_policy")
+        {
+            parse_key(input, "kwarg_policy")?;
+            kwarg_policy = Some(input.parse()?);
+            input.parse::<Token![,]>()?;
+        }
+
+        parse_key(input, "args")?;
+        let content;
+        bracketed!(content in input);
+        let args = Punctuated::<ArgDef, Token![,]>::parse_terminated(&content)?;
+
+        let mut examples = Punctuated::<ExampleDef, Token![,]>::new();
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.peek(Ident) {
+                parse_key(input, "examples")?;
+                let content;
+                bracketed!(content in input);
+                examples = Punctuated::parse_terminated(&content)?;
+            }
+        }
+
+        Ok(CommandDef {
+            name,
+            desc,
+            kwarg_policy,
+            args: args.into_iter().collect(),
+            examples: examples.into_iter().collect(),
+        })
+    }
+}
+
+impl Parse for ArgDef {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let content;
+        let bracket = bracketed!(content in input);
+
+        let name: Expr = content.parse()?;
+        content.parse::<Token![,]>()?;
+        let desc: Expr = content.parse()?;
+        content.parse::<Token![,]>()?;
+        let arg_type: Expr = content.parse()?;
+        content.parse::<Token![,]>()?;
+        let data_type: Type = content.parse()?;
+        content.parse::<Token![,]>()?;
+        let kind: Expr = content.parse()?;
+        content.parse::<Token![,]>()?;
+        let default: Expr = content.parse()?;
+
+        // `option: value` pairs, set with the `Arg` method of the same name
+        let mut options = Vec::new();
+        while !content.is_empty() {
+            content.parse::<Token![,]>()?;
+            if content.is_empty() {
+                break;
+            }
+            let option: Ident = content.parse()?;
+            content.parse::<Token![:]>()?;
+            options.push((option, content.parse()?));
+        }
+
+        Ok(ArgDef {
+            span: bracket.span,
+            name,
+            desc,
+            arg_type,
+            data_type,
+            kind,
+            default,
+            options,
+        })
+    }
+}
+
+/// An example, either an invocation that should parse or
+/// `[invocation, Ok|Err|Err(message)]`.
+pub struct ExampleDef {
+    pub invocation: Expr,
+    pub fails: bool,
+}
+
+impl Parse for ExampleDef {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if !input.peek(syn::token::Bracket) {
+            return Ok(ExampleDef {
+                invocation: input.parse()?,
+                fails: false,
+            });
+        }
+
+        let content;
+        bracketed!(content in input);
+        let invocation: Expr = content.parse()?;
+        content.parse::<Token![,]>()?;
+
+        let expect: Ident = content.parse()?;
+        let fails = match expect.to_string().as_str() {
+            "Ok" => false,
+            "Err" => {
+                if content.peek(syn::token::Paren) {
+                    let msg;
+                    syn::parenthesized!(msg in content);
+                    msg.parse::<Expr>()?;
+                }
+                true
+            }
+            _ => return Err(syn::Error::new(expect.span(), "expected `Ok` or `Err`")),
+        };
+
+        if !content.is_empty() {
+            return Err(content.error("unexpected tokens after the expected result"));
+        }
+
+        Ok(ExampleDef { invocation, fails })
+    }
+}
+
+impl ArgDef {
+    /// Value of an option, e.g. `max_len: 100`.
+    pub fn option(&self, name: &str) -> Option<&Expr> {
+        self.options.iter().find(|(o, _)| o == name).map(|(_, v)| v)
+    }
+
+    /// Whether the data type is a JSON document, i.e. `serde_json::Value`.
+    pub fn is_json(&self) -> bool {
+        match &self.data_type {
+            syn::Type::Path(p) => p.path.segments.last().is_some_and(|s| s.ident == "Value"),
+            _ => false,
+        }
+    }
+
+    /// Type given by the `schema` option, e.g. `Config` for
+    /// `Schema::of::<Config>()`.
+    pub fn schema_type(&self) -> Option<&Type> {
+        let func = match strip_groups(self.option("schema")?) {
+            Expr::Call(call) => strip_groups(&call.func),
+            _ => return None,
+        };
+        let seg = match func {
+            Expr::Path(p) => p.path.segments.last()?,
+            _ => return None,
+        };
+        let ty = match &seg.arguments {
+            syn::PathArguments::AngleBracketed(args) => args.args.first()?,
+            _ => return None,
+        };
+        match ty {
+            syn::GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        }
+    }
+
+    /// Argument of the collection, i.e. the key type of a map like `String`
+    /// for `Collection::Map(String)`, or the length of an array like `128`
+    /// for `Collection::Array(128)`.
+    pub fn kind_arg(&self) -> Option<&Expr> {
+        match strip_groups(&self.kind) {
+            Expr::Call(call) if call.args.len() == 1 => Some(&call.args[0]),
+            _ => None,
+        }
+    }
+
+    /// Values of a collection default, i.e. the elements of its `vec!`, e.g.
+    /// `Box::new(1_u64) as Box<dyn Value>` for
+    /// `Some(Box::new(vec![Box::new(1_u64) as Box<dyn Value>]))`. The values
+    /// of a `Set` default are taken from its `values`, and those of a `Map`
+    /// default are pairs. Defaults without a `vec!` have no values here.
+    pub fn default_values(&self) -> Vec<Expr> {
+        let mut expr = strip_groups(&self.default);
+        loop {
+            expr = match expr {
+                Expr::Call(call) if call.args.len() == 1 => {
+                    let func = call.func.to_token_stream().to_string().replace(' ', "");
+                    if func != "Some" && !func.ends_with("Box::new") {
+                        return Vec::new();
+                    }
+                    strip_groups(&call.args[0])
+                }
+                Expr::Struct(s) => match s.fields.iter().find(|f| is_member(f, "values")) {
+                    Some(values) => strip_groups(&values.expr),
+                    None => return Vec::new(),
+                },
+                Expr::Macro(m) if m.mac.path.is_ident("vec") => {
+                    return m
+                        .mac
+                        .parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated)
+                        .map(|values| values.into_iter().collect())
+                        .unwrap_or_default();
+                }
+                _ => return Vec::new(),
+            };
+        }
+    }
+
+    /// Whether the arg has a default, i.e. isn't `None`.
+    pub fn is_optional(&self) -> bool {
+        match strip_groups(&self.default) {
+            Expr::Path(p) => !p.path.is_ident("None"),
+            _ => true,
+        }
+    }
+}
+
+/// Parses `key:`.
+fn parse_key(input: ParseStream, key: &str) -> syn::Result<()> {
+    let ident: Ident = input.parse()?;
+    if ident != key {
+        return Err(syn::Error::new(ident.span(), format!("expected `{}`", key)));
+    }
+    input.parse::<Token![:]>()?;
+    Ok(())
+}
+
+fn is_member(field: &syn::FieldValue, name: &str) -> bool {
+    match &field.member {
+        syn::Member::Named(m) => m == name,
+        _ => false,
+    }
+}
+
+/// Value of an integer literal expression.
+pub fn lit_int(expr: &Expr) -> Option<String> {
+    match strip_groups(expr) {
+        Expr::Lit(l) => match &l.lit {
+            syn::Lit::Int(i) => Some(i.base10_digits().to_owned()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Value of a string literal expression.
+pub fn lit_str(expr: &Expr) -> Option<String> {
+    match strip_groups(expr) {
+        Expr::Lit(l) => match &l.lit {
+            syn::Lit::Str(s) => Some(s.value()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Variant name of an enum variant path expression, e.g. `Arg` for
+/// `ArgType::Arg`, or `Map` for `Collection::Map(String)`.
+pub fn variant(expr: &Expr) -> Option<String> {
+    match strip_groups(expr) {
+        Expr::Path(p) => p.path.segments.iter().last().map(|s| s.ident.to_string()),
+        Expr::Call(call) => variant(&call.func),
+        _ => None,
+    }
+}
+
+/// Looks through the invisible groups `macro_rules` wraps around captured
+/// expressions, as well as parentheses.
+pub fn strip_groups(expr: &Expr) -> &Expr {
+    match expr {
+        Expr::Group(g) => strip_groups(&g.expr),
+        Expr::Paren(p) => strip_groups(&p.expr),
+        _ => expr,
+    }
+}
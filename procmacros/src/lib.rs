@@ -1,226 +1,654 @@
 extern crate proc_macro;
 use proc_macro::TokenStream;
 
-use syn::parse::Parser;
-use syn::{punctuated, Expr, ItemConst, Token};
-
-use std::fs::{create_dir_all, remove_file, OpenOptions};
-use std::io::prelude::*;
+use proc_macro2::{Span, TokenTree};
+use quote::ToTokens;
+use syn::punctuated::Punctuated;
+use syn::{Expr, Token};
+
+use std::env;
+use std::fs::{create_dir_all, read_to_string, rename, write};
+use std::io;
 use std::path::Path;
+use std::process;
+
+mod def;
+mod validate;
+
+use def::{lit_int, lit_str, strip_groups, variant, ArgDef, CommandDef};
 
 #[derive(Debug)]
 struct Command {
     name: String,
     desc: String,
+    kwarg_policy: String,
     args: Vec<Arg>,
+    examples: Vec<String>,
 }
 
 #[derive(Debug)]
 struct Arg {
     name: String,
+    token: String,
     desc: String,
     arg_type: String,
     data_type: String,
     kind: String,
+    key_type: Option<String>,
+    len: Option<String>,
     optional: bool,
+    default: Option<String>,
+    max_len: Option<String>,
+    reject_duplicates: bool,
 }
 
+/// Writes the section of the command reference of a command to
+/// `doc/commands/<command>.md`, relative to the crate being built.
+///
+/// The attribute can be put on any item that contains a `command!` or
+/// `try_command!` invocation, e.g. a `static` (including inside
+/// `thread_local!`) or a `fn` returning a `Command`. Every `command!` in the item is documented, including those
+/// bound with `let` in a `fn`, as attributes on statements aren't stable.
+///
+/// Each file only depends on the definition of its own command, so the order
+/// in which macros are expanded, and how often, doesn't change the output.
+/// Macro expansion never sees all commands of a crate, e.g. with lazy
+/// expansion in rust-analyzer or commands behind a `cfg`, so nothing is ever
+/// removed here: `docgen::remove_stale_sections` does that with the full list
+/// of commands, and `docgen::write_reference` writes the command reference.
+/// The `clean` arg of earlier versions is accepted and ignored.
+///
+/// Sections are rendered from the tokens of the definition, so defaults are
+/// only shown like `Command::to_markdown` does when they are spelled out as
+/// literals, e.g. `Some(Box::new(1_u64))`, or as values of the data types of
+/// redis commands. Other defaults, e.g. calls of a helper fn, are shown as
+/// written. The command reference written by `docgen::write_reference` is the
+/// authoritative one.
 #[proc_macro_attribute]
 pub fn rediscmd_doc(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let mut output = String::new();
-
-    let parsed: ItemConst = syn::parse(item.clone()).unwrap();
-    if let Expr::Macro(mac) = *parsed.expr {
-        for token in mac.mac.tokens.into_iter() {
-            if let proc_macro2::TokenTree::Group(g) = token {
-                let cmd = parse_command(g.stream());
-                output = stringify_command(cmd);
-                break;
+    let documented = commands(attr.into(), item.clone().into()).map(|cmds| {
+        for cmd in cmds {
+            let name = cmd.name.clone();
+            if let Err(e) = write_doc(cmd) {
+                eprintln!("Couldn't write the section of {}: {}", name, e);
             }
         }
+    });
+    match documented {
+        Ok(_) => item,
+        Err(e) => {
+            let mut output = item;
+            output.extend(TokenStream::from(e.to_compile_error()));
+            output
+        }
+    }
+}
+
+/// Checks a `command!` definition at compile time. Used by `command!`, which
+/// passes its body as is.
+#[doc(hidden)]
+#[proc_macro]
+pub fn validate_command(input: TokenStream) -> TokenStream {
+    let def = syn::parse_macro_input!(input as CommandDef);
+    match validate::validate(&def) {
+        Ok(checks) => checks.into(),
+        Err(e) => e.to_compile_error().into(),
     }
+}
 
-    let filepath = Path::new("doc").join("COMMAND_REFERENCE_GEN.md");
+/// Commands of the `command!` invocations in an item marked with
+/// `rediscmd_doc`.
+fn commands(
+    attr: proc_macro2::TokenStream,
+    item: proc_macro2::TokenStream,
+) -> syn::Result<Vec<Command>> {
+    if !attr.is_empty() && attr.to_string() != "clean" {
+        return Err(syn::Error::new_spanned(
+            attr,
+            "rediscmd_doc takes no arguments",
+        ));
+    }
 
-    // delete file if first attr is passed
-    if &attr.to_string() == "clean" {
-        match remove_file(filepath.clone()) {
-            Ok(_) => (),
-            Err(e) => println!("Could not delete {:?}: {}", filepath.to_str(), e),
+    let mut bodies = Vec::new();
+    find_commands(item, &mut bodies);
+    if bodies.is_empty() {
+        return Err(syn::Error::new(
+            Span::call_site(),
+            "rediscmd_doc expects an item containing a `command!` invocation",
+        ));
+    }
+
+    bodies
+        .into_iter()
+        .map(|body| Command::from_def(&syn::parse2(body)?))
+        .collect()
+}
+
+/// Collects the bodies of all `command!` and `try_command!` invocations in
+/// `tokens`, looking into nested groups, e.g. function bodies or other macro
+/// invocations.
+fn find_commands(tokens: proc_macro2::TokenStream, bodies: &mut Vec<proc_macro2::TokenStream>) {
+    let tokens: Vec<TokenTree> = tokens.into_iter().collect();
+    for (i, tt) in tokens.iter().enumerate() {
+        if let TokenTree::Group(g) = tt {
+            let is_command = i >= 2
+                && matches!(&tokens[i - 1], TokenTree::Punct(p) if p.as_char() == '!')
+                && matches!(&tokens[i - 2], TokenTree::Ident(id) if id == "command" || id == "try_command");
+            if is_command {
+                bodies.push(g.stream());
+            } else {
+                find_commands(g.stream(), bodies);
+            }
         }
     }
+}
 
-    // write out markdown
-    create_dir_all("doc").unwrap();
-    let mut file = OpenOptions::new()
-        .append(true)
-        .create(true)
-        .open(filepath.to_str().unwrap())
-        .unwrap();
-    if let Err(e) = writeln!(file, "{}", output) {
-        eprintln!("Couldn't write to file: {}", e);
-    };
+fn write_doc(cmd: Command) -> io::Result<()> {
+    let root = env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".to_owned());
+    let name = cmd.name.clone();
+    write_section(
+        &Path::new(&root).join("doc").join("commands"),
+        &name,
+        &stringify_command(cmd),
+    )
+}
 
-    item
+/// Writes the section of a command to its file in `dir`.
+fn write_section(dir: &Path, name: &str, section: &str) -> io::Result<()> {
+    create_dir_all(dir)?;
+    write_if_changed(&dir.join(section_file(name)), section)
 }
 
-fn parse_command(tokens: proc_macro2::TokenStream) -> Command {
-    let mut name = String::new();
-    let mut desc = String::new();
-    let mut args: Vec<Arg> = Vec::new();
+/// Name of the file holding the section of a command, the same as
+/// `docgen::remove_stale_sections` expects.
+fn section_file(name: &str) -> String {
+    let filename: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | '0'..='9' | '.' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect();
+    format!("{}.md", filename)
+}
 
-    let mut pos: usize = 0;
-    for tt in tokens.into_iter() {
-        match tt {
-            proc_macro2::TokenTree::Literal(l) => {
-                if pos == 0 {
-                    name = l.to_string();
-                }
-                if pos == 1 {
-                    desc = l.to_string();
-                }
-                pos += 1
-            }
-            proc_macro2::TokenTree::Group(g) => {
-                args = parse_args(g.stream());
-            }
-            _ => (),
+/// Atomically replaces the file at `path`, unless it already has `contents`.
+fn write_if_changed(path: &Path, contents: &str) -> io::Result<()> {
+    if let Ok(current) = read_to_string(path) {
+        if current == contents {
+            return Ok(());
         }
     }
 
-    Command { name, desc, args }
+    // macros may be expanded concurrently, e.g. by cargo and rust-analyzer
+    let tmp = path.with_extension(format!("{}.tmp", process::id()));
+    write(&tmp, contents)?;
+    rename(&tmp, path)
 }
 
-fn parse_args(tokens: proc_macro2::TokenStream) -> Vec<Arg> {
-    let mut args: Vec<Arg> = Vec::new();
+impl Command {
+    /// Builds the documented command, which needs literal names and
+    /// descriptions.
+    fn from_def(def: &CommandDef) -> syn::Result<Self> {
+        let mut args = Vec::new();
+        for arg in def.args.iter() {
+            args.push(Arg::from_def(arg)?);
+        }
 
-    for tt in tokens.into_iter() {
-        if let proc_macro2::TokenTree::Group(g) = tt {
-            let parser = punctuated::Punctuated::<Expr, Token![,]>::parse_terminated;
-            let parsed = parser.parse2(g.stream()).unwrap();
+        // only invocations that parse are shown
+        let mut examples = Vec::new();
+        for example in def.examples.iter().filter(|e| !e.fails) {
+            examples.push(expect_lit_str(&example.invocation)?);
+        }
 
-            let mut name = String::new();
-            let mut desc = String::new();
-            let mut arg_type = String::new();
-            let mut data_type = String::new();
-            let mut kind = String::new();
-            let mut optional = true;
+        let kwarg_policy = match &def.kwarg_policy {
+            Some(p) => expect_variant(p, "KwargPolicy", &["Greedy", "Positional", "Terminator"])?,
+            None => "Greedy".to_owned(),
+        };
+
+        Ok(Command {
+            name: expect_lit_str(&def.name)?,
+            desc: expect_lit_str(&def.desc)?,
+            kwarg_policy,
+            args,
+            examples,
+        })
+    }
+}
 
-            let mut cursor = parsed.iter();
+impl Arg {
+    fn from_def(def: &ArgDef) -> syn::Result<Self> {
+        let data_type = match &def.data_type {
+            _ if def.is_json() => match def.schema_type() {
+                Some(schema) => format!("JSON<{}>", short_type_name(schema)),
+                None => "JSON".to_owned(),
+            },
+            t => short_type_name(t),
+        };
+        let kind = expect_variant(
+            &def.kind,
+            "Collection",
+            &["Unit", "Vec", "Set", "Array", "Map"],
+        )?;
+        let (key_type, len) = match (kind.as_str(), def.kind_arg()) {
+            ("Map", Some(key)) => (
+                Some(short_type_name(&syn::parse2(key.to_token_stream())?)),
+                None,
+            ),
+            ("Array", Some(len)) => (
+                None,
+                Some(lit_int(len).unwrap_or_else(|| len.to_token_stream().to_string())),
+            ),
+            _ => (None, None),
+        };
+
+        let optional = def.is_optional();
+        let default = if optional {
+            Some(default_value(&def.default, &data_type, key_type.as_deref()))
+        } else {
+            None
+        };
+
+        let name = expect_lit_str(&def.name)?;
+        let token = match def.option("token") {
+            Some(t) => expect_lit_str(t)?,
+            None => name.clone(),
+        };
+
+        Ok(Arg {
+            name,
+            token,
+            desc: expect_lit_str(&def.desc)?,
+            arg_type: expect_variant(&def.arg_type, "ArgType", &["Arg", "Kwarg"])?,
+            data_type,
+            kind,
+            key_type,
+            len,
+            optional,
+            default,
+            max_len: def
+                .option("max_len")
+                .map(|v| lit_int(v).unwrap_or_else(|| v.to_token_stream().to_string())),
+            reject_duplicates: def
+                .option("duplicates")
+                .is_some_and(|d| variant(d).as_deref() == Some("Reject")),
+        })
+    }
+}
 
-            let name_expr = cursor.next().unwrap();
-            if let Expr::Lit(l) = name_expr {
-                if let syn::Lit::Str(s) = &l.lit {
-                    name = s.value()
-                }
-            }
+/// Name of a type without its module paths, including those of generic
+/// arguments, e.g. `Vec<Config>` for `Vec<my_module::Config>`.
+fn short_type_name(ty: &syn::Type) -> String {
+    match ty {
+        syn::Type::Path(p) => match p.path.segments.iter().last() {
+            Some(seg) => match &seg.arguments {
+                syn::PathArguments::AngleBracketed(args) => format!(
+                    "{}<{}>",
+                    seg.ident,
+                    args.args
+                        .iter()
+                        .map(|a| match a {
+                            syn::GenericArgument::Type(t) => short_type_name(t),
+                            a => a.to_token_stream().to_string(),
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                _ => seg.ident.to_string(),
+            },
+            None => p.to_token_stream().to_string(),
+        },
+        syn::Type::Tuple(t) => format!(
+            "({})",
+            t.elems
+                .iter()
+                .map(short_type_name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        syn::Type::Array(a) => format!(
+            "[{}; {}]",
+            short_type_name(&a.elem),
+            a.len.to_token_stream()
+        ),
+        syn::Type::Group(g) => short_type_name(&g.elem),
+        t => t.to_token_stream().to_string(),
+    }
+}
 
-            let desc_expr = cursor.next().unwrap();
-            if let Expr::Lit(l) = desc_expr {
-                if let syn::Lit::Str(s) = &l.lit {
-                    desc = s.value()
-                }
-            }
+fn expect_lit_str(expr: &Expr) -> syn::Result<String> {
+    lit_str(expr).ok_or_else(|| syn::Error::new_spanned(expr, "expected a string literal"))
+}
 
-            let at_expr = cursor.next().unwrap();
-            if let Expr::Path(p) = at_expr {
-                let mut segs = p.path.segments.iter();
-                if let Some(seg) = segs.next() {
-                    if &seg.ident.to_string() == "ArgType" {
-                        if let Some(t) = segs.next() {
-                            arg_type = t.ident.to_string();
-                        }
-                    }
-                }
-            }
+/// Checks that `expr` is a path to one of the `variants` of enum `ty`,
+/// e.g. `ArgType::Arg`.
+fn expect_variant(expr: &Expr, ty: &str, variants: &[&str]) -> syn::Result<String> {
+    if let Some(v) = variant(expr) {
+        if variants.contains(&v.as_str()) {
+            return Ok(v);
+        }
+    }
 
-            let dt_expr = cursor.next().unwrap();
-            if let Expr::Path(p) = dt_expr {
-                let mut segs = p.path.segments.iter();
-                if let Some(seg) = segs.next() {
-                    data_type = seg.ident.to_string();
-                }
-            }
+    let expected: Vec<String> = variants
+        .iter()
+        .map(|v| format!("`{}::{}`", ty, v))
+        .collect();
+    Err(syn::Error::new_spanned(
+        expr,
+        format!("expected one of {}", expected.join(", ")),
+    ))
+}
 
-            let kind_expr = cursor.next().unwrap();
-            if let Expr::Path(p) = kind_expr {
-                let mut segs = p.path.segments.iter();
-                if let Some(seg) = segs.next() {
-                    if &seg.ident.to_string() == "Collection" {
-                        if let Some(t) = segs.next() {
-                            kind = t.ident.to_string();
-                        }
-                    }
-                }
-            }
+/// Renders the value of a default expression like the runtime reference
+/// does, i.e. as the `Debug` output of the boxed value, e.g.
+/// `Some(Box::new("foo".to_owned()))` as `"foo"`, with data types of redis
+/// commands shown as they are typed. The values of collections are taken from
+/// their `vec!`, with the keys of maps rendered as `key_type`.
+fn default_value(expr: &Expr, data_type: &str, key_type: Option<&str>) -> String {
+    let expr = unwrap_value(expr);
+
+    // empty collections, e.g. `Pairs::new()`
+    if let Expr::Call(call) = expr {
+        let path = call.func.to_token_stream().to_string().replace(' ', "");
+        let empty = ["Pairs::new", "Vec::new", "Set::default"];
+        if call.args.is_empty() && empty.iter().any(|e| path.ends_with(e)) {
+            return "[]".to_owned();
+        }
+    }
 
-            let default_expr = cursor.next().unwrap();
-            if let Expr::Path(p) = default_expr {
-                let mut segs = p.path.segments.iter();
-                if let Some(seg) = segs.next() {
-                    if &seg.ident.to_string() == "None" {
-                        optional = false;
-                    }
+    // sets are shown as their values
+    if let Expr::Struct(s) = expr {
+        let values = s.fields.iter().find(|f| match &f.member {
+            syn::Member::Named(m) => m == "values",
+            _ => false,
+        });
+        if let Some(values) = values {
+            return default_value(&values.expr, data_type, key_type);
+        }
+    }
+
+    if let Expr::Macro(m) = expr {
+        let values = m
+            .mac
+            .parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated);
+        if let (true, Ok(values)) = (m.mac.path.is_ident("vec"), values) {
+            let values: Vec<String> = values
+                .iter()
+                .map(|v| match unwrap_value(v) {
+                    Expr::Tuple(pair) if pair.elems.len() == 2 => format!(
+                        "({}, {})",
+                        debug_value(&pair.elems[0], key_type.unwrap_or(data_type)),
+                        debug_value(&pair.elems[1], data_type)
+                    ),
+                    v => debug_value(v, data_type),
+                })
+                .collect();
+            return format!("[{}]", values.join(", "));
+        }
+    }
+
+    raw_value(expr, data_type).unwrap_or_else(|| debug_value(expr, data_type))
+}
+
+/// Looks through the wrappers of a value, i.e. `Some(..)`, `Box::new(..)`,
+/// `String::from(..)`, `.to_owned()` and casts to `Box<dyn Value>`.
+fn unwrap_value(expr: &Expr) -> &Expr {
+    let mut expr = strip_groups(expr);
+    loop {
+        match expr {
+            Expr::Call(call) if call.args.len() == 1 => {
+                let path = call.func.to_token_stream().to_string().replace(' ', "");
+                if path == "Some" || path.ends_with("Box::new") || path.ends_with("String::from") {
+                    expr = strip_groups(&call.args[0]);
+                } else {
+                    return expr;
                 }
             }
+            Expr::MethodCall(m)
+                if m.args.is_empty()
+                    && ["to_owned", "to_string", "into"].contains(&&*m.method.to_string()) =>
+            {
+                expr = strip_groups(&m.receiver);
+            }
+            Expr::Cast(cast) => expr = strip_groups(&cast.expr),
+            _ => return expr,
+        }
+    }
+}
 
-            args.push(Arg {
-                name,
-                desc,
-                arg_type,
-                data_type,
-                kind,
-                optional,
-            })
+/// Raw form of a value of one of the data types of redis commands, spelled
+/// out as a literal or a variant, e.g. `-` for `StreamId::Min`.
+fn raw_value(expr: &Expr, data_type: &str) -> Option<String> {
+    match (data_type, expr) {
+        (_, Expr::Path(p)) => {
+            let variant = p.path.segments.iter().last()?.ident.to_string();
+            let raw = match (data_type, variant.as_str()) {
+                ("StreamId", "Min") | ("LexBound", "NegInfinity") => "-",
+                ("StreamId", "Max") | ("LexBound", "PosInfinity") => "+",
+                ("StreamId", "Auto") => "*",
+                ("StreamId", "Last") => "$",
+                ("StreamId", "Undelivered") => ">",
+                ("DistanceUnit", unit) => return Some(unit.to_lowercase()),
+                _ => return None,
+            };
+            Some(raw.to_owned())
+        }
+        ("Longitude" | "Latitude", Expr::Call(call)) if call.args.len() == 1 => {
+            let degrees = debug_value(&call.args[0], "f64").parse::<f64>().ok()?;
+            Some(degrees.to_string())
+        }
+        ("MemorySize", Expr::Call(call)) if call.args.len() == 1 => {
+            // with the largest unit that fits, like `MemorySize`
+            let bytes = lit_int(&call.args[0])?.parse::<u64>().ok()?;
+            let (suffix, shift) = match bytes.trailing_zeros() {
+                64 => ("", 0),
+                z if z >= 30 => ("gb", 30),
+                z if z >= 20 => ("mb", 20),
+                z if z >= 10 => ("kb", 10),
+                _ => ("", 0),
+            };
+            Some(format!("{}{}", bytes >> shift, suffix))
         }
+        _ => None,
     }
+}
+
+/// Renders a literal value like its `Debug` output, or the expression itself
+/// if it isn't a literal.
+fn debug_value(expr: &Expr, data_type: &str) -> String {
+    let expr = unwrap_value(expr);
+    let (neg, lit) = match expr {
+        Expr::Lit(l) => ("", &l.lit),
+        Expr::Unary(syn::ExprUnary {
+            op: syn::UnOp::Neg(_),
+            expr: inner,
+            ..
+        }) => match strip_groups(inner) {
+            Expr::Lit(l) => ("-", &l.lit),
+            _ => return expr.to_token_stream().to_string(),
+        },
+        _ => return expr.to_token_stream().to_string(),
+    };
 
-    args
+    match lit {
+        syn::Lit::Str(s) => format!("{:?}", s.value()),
+        syn::Lit::Int(i) if data_type == "f64" => match i.base10_parse::<f64>() {
+            Ok(f) => format!("{}{:?}", neg, f),
+            Err(_) => lit.to_token_stream().to_string(),
+        },
+        syn::Lit::Int(i) => format!("{}{}", neg, i.base10_digits()),
+        syn::Lit::Float(f) => match f.base10_parse::<f64>() {
+            Ok(f) => format!("{}{:?}", neg, f),
+            Err(_) => lit.to_token_stream().to_string(),
+        },
+        _ => lit.to_token_stream().to_string(),
+    }
 }
 
+/// Renders the section of a command like `Command::to_markdown` does at
+/// runtime, as far as the tokens of its definition tell. The `docgen` example
+/// of `redismodule_cmd` checks that both agree for defaults of every form
+/// `default_value` knows.
 fn stringify_command(cmd: Command) -> String {
-    let name = cmd.name.to_uppercase().replace("\"", "");
-    let desc = cmd.desc.replace("\"", "");
-    let args = stringify_args(cmd.args);
+    let name = cmd.name.to_uppercase();
+    let desc = cmd.desc;
+
+    // document args in the same order as the runtime reference: required
+    // args, optional args, then kwargs in alphabetical order of keywords,
+    // with kwargs sharing a keyword in the order they are defined
+    let mut cmd_args = cmd.args;
+    cmd_args.sort_by_key(|a| match (a.arg_type == "Kwarg", a.optional) {
+        (false, false) => (0, String::new()),
+        (false, true) => (1, String::new()),
+        (true, _) => (2, a.token.clone()),
+    });
+
+    let usage = usage(&name, &cmd_args, cmd.kwarg_policy == "Terminator");
+    let args = stringify_args(cmd_args);
+    let examples = if cmd.examples.is_empty() {
+        String::new()
+    } else {
+        format!("#### Example\n```\n{}\n```\n", cmd.examples.join("\n"))
+    };
 
     let output = format!(
         "
 ### {name}
 #### Format
 ```
-placeholder
+{usage}
 ```
 #### Description
 {desc}
-#### Example
-```
-placeholder
-```
-#### Parameters
+{examples}#### Parameters
 {args}
 ",
         name = name,
+        usage = usage,
         desc = desc,
+        examples = examples,
         args = args
     );
 
     output
 }
 
+/// Builds the usage line the same way `Command`'s `Display` impl does:
+/// required args, then optional args, then kwargs in alphabetical order,
+/// separated by `[--]` with a terminator.
+fn usage(name: &str, args: &[Arg], terminator: bool) -> String {
+    let mut output = name.to_owned();
+
+    let required = args.iter().filter(|a| a.arg_type != "Kwarg" && !a.optional);
+    let optional: Vec<&Arg> = args
+        .iter()
+        .filter(|a| a.arg_type != "Kwarg" && a.optional)
+        .collect();
+    // kwargs are sorted by keyword already
+    let kwargs: Vec<&Arg> = args.iter().filter(|a| a.arg_type == "Kwarg").collect();
+
+    for arg in required.chain(optional.iter().copied()) {
+        output.push(' ');
+        output.push_str(&optional_usage(arg.optional, usage_arg(arg)));
+    }
+    if terminator && !optional.is_empty() && !kwargs.is_empty() {
+        output.push_str(" [--]");
+    }
+    let mut i = 0;
+    while i < kwargs.len() {
+        let token = &kwargs[i].token;
+        let mut usage = token.to_uppercase();
+        let optional = kwargs[i].optional;
+        while i < kwargs.len() && &kwargs[i].token == token {
+            usage = format!("{} {}", usage, usage_arg(kwargs[i]));
+            i += 1;
+        }
+        output.push(' ');
+        output.push_str(&optional_usage(optional, usage));
+    }
+
+    output
+}
+
+/// Usage fragment of the values of an arg, without its keyword.
+fn usage_arg(arg: &Arg) -> String {
+    match arg.kind.as_str() {
+        "Vec" | "Set" => format!("count {arg} [{arg} ...]", arg = arg.name),
+        "Array" => match arg.len.as_deref().unwrap_or_default() {
+            "1" => arg.name.clone(),
+            "2" => format!("{arg}_1 {arg}_2", arg = arg.name),
+            len => format!("{arg}_1 ... {arg}_{len}", arg = arg.name, len = len),
+        },
+        "Map" => format!("count field {arg} [field {arg} ...]", arg = arg.name),
+        _ => arg.name.clone(),
+    }
+}
+
+fn optional_usage(optional: bool, usage: String) -> String {
+    if optional {
+        format!("[{}]", usage)
+    } else {
+        usage
+    }
+}
+
 fn stringify_args(args: Vec<Arg>) -> String {
     let mut output = String::new();
+    if !args.is_empty() {
+        output.push_str(
+            "
+| Name | Keyword | Type | Kind | Collection | Required | Default | Description |
+| --- | --- | --- | --- | --- | --- | --- | --- |
+",
+        );
+    }
+
     for arg in args {
-        let name = arg.name.to_uppercase();
-        let desc = arg.desc;
-        let optional = if arg.optional { "Optional" } else { "Required" };
+        let (kind, keyword) = if arg.arg_type == "Kwarg" {
+            ("Keyword", arg.token.to_uppercase())
+        } else {
+            ("Positional", String::new())
+        };
+        let mut collection = match (arg.kind.as_str(), &arg.max_len) {
+            ("Vec", Some(max_len)) => format!("Vec: a count, then at most {} values", max_len),
+            ("Vec", None) => "Vec: a count, then that many values".to_owned(),
+            ("Set", Some(max_len)) => format!("Set: a count, then at most {} values", max_len),
+            ("Set", None) => "Set: a count, then that many values".to_owned(),
+            ("Array", _) => format!(
+                "Array: exactly {} values",
+                arg.len.as_deref().unwrap_or_default()
+            ),
+            ("Map", max_len) => format!(
+                "Map: a count, then {} pairs of a `{}` key and a value",
+                max_len
+                    .as_ref()
+                    .map_or("that many".to_owned(), |m| format!("at most {}", m)),
+                arg.key_type.as_deref().unwrap_or_default()
+            ),
+            _ => "Single value".to_owned(),
+        };
+        if arg.reject_duplicates {
+            match arg.kind.as_str() {
+                "Set" => collection.push_str(", without duplicate values"),
+                _ => collection.push_str(", without duplicate keys"),
+            }
+        }
+        let (required, default) = match arg.default {
+            Some(d) => ("Optional", format!("`{}`", d)),
+            None => ("Required", String::new()),
+        };
 
         let arg_out = format!(
-            "
-* **{name}**: {optional}. {desc}
-",
-            name = name,
-            desc = desc,
-            optional = optional
+            "| {name} | {keyword} | `{data_type}` | {kind} | {collection} | {required} | {default} | {desc} |\n",
+            name = arg.name,
+            keyword = keyword,
+            data_type = arg.data_type,
+            kind = kind,
+            collection = collection,
+            required = required,
+            default = default.replace('|', "\\|"),
+            desc = arg.desc.replace('|', "\\|")
         );
 
         output.push_str(&arg_out);
@@ -228,3 +656,227 @@ fn stringify_args(args: Vec<Arg>) -> String {
 
     output
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{commands, default_value, stringify_command, write_section, Command};
+    use crate::def::CommandDef;
+    use std::env;
+    use std::fs::{read_dir, read_to_string, remove_dir_all};
+    use std::process;
+
+    fn command(def: &str) -> Command {
+        let def: CommandDef = syn::parse_str(def).unwrap();
+        Command::from_def(&def).unwrap()
+    }
+
+    const SAMPLE: &str = r#"
+        name: "doc.sample",
+        desc: "a sample | command",
+        kwarg_policy: KwargPolicy::Terminator,
+        args: [
+            ["key", "a key", ArgType::Arg, String, Collection::Unit, None],
+            ["scores", "some scores", ArgType::Arg, f64, Collection::Array(3), None],
+            ["start", "where to start", ArgType::Arg, StreamId, Collection::Unit, Some(Box::new(StreamId::Min))],
+            ["ratio", "a ratio", ArgType::Arg, f64, Collection::Unit, Some(Box::new(0.5))],
+            ["limit_offset", "an offset", ArgType::Kwarg, u64, Collection::Unit, Some(Box::new(0_u64)), token: "limit"],
+            ["limit_count", "a count", ArgType::Kwarg, i64, Collection::Unit, Some(Box::new(-10_i64)), token: "limit"],
+            ["tags", "some tags", ArgType::Kwarg, String, Collection::Set, Some(Box::new(Set::default())), duplicates: Duplicates::Reject],
+            ["attrs", "some attributes", ArgType::Kwarg, u64, Collection::Map(String), Some(Box::new(Pairs::new())), max_len: 10],
+            ["vec1", "a vec", ArgType::Kwarg, i64, Collection::Vec, None],
+            ["name", "a name", ArgType::Kwarg, String, Collection::Unit, Some(Box::new("foo".to_owned()))],
+        ],
+        examples: [
+            "doc.sample k 1 2 3 vec1 0",
+            ["doc.sample k", Err],
+        ],
+    "#;
+
+    #[test]
+    fn stringify_command_test() {
+        assert_eq!(
+            stringify_command(command(SAMPLE)),
+            r#"
+### DOC.SAMPLE
+#### Format
+```
+DOC.SAMPLE key scores_1 ... scores_3 [start] [ratio] [--] [ATTRS count field attrs [field attrs ...]] [LIMIT limit_offset limit_count] [NAME name] [TAGS count tags [tags ...]] VEC1 count vec1 [vec1 ...]
+```
+#### Description
+a sample | command
+#### Example
+```
+doc.sample k 1 2 3 vec1 0
+```
+#### Parameters
+
+| Name | Keyword | Type | Kind | Collection | Required | Default | Description |
+| --- | --- | --- | --- | --- | --- | --- | --- |
+| key |  | `String` | Positional | Single value | Required |  | a key |
+| scores |  | `f64` | Positional | Array: exactly 3 values | Required |  | some scores |
+| start |  | `StreamId` | Positional | Single value | Optional | `-` | where to start |
+| ratio |  | `f64` | Positional | Single value | Optional | `0.5` | a ratio |
+| attrs | ATTRS | `u64` | Keyword | Map: a count, then at most 10 pairs of a `String` key and a value | Optional | `[]` | some attributes |
+| limit_offset | LIMIT | `u64` | Keyword | Single value | Optional | `0` | an offset |
+| limit_count | LIMIT | `i64` | Keyword | Single value | Optional | `-10` | a count |
+| name | NAME | `String` | Keyword | Single value | Optional | `"foo"` | a name |
+| tags | TAGS | `String` | Keyword | Set: a count, then that many values, without duplicate values | Optional | `[]` | some tags |
+| vec1 | VEC1 | `i64` | Keyword | Vec: a count, then that many values | Required |  | a vec |
+
+"#
+        );
+    }
+
+    #[test]
+    fn data_type_test() {
+        let cmd = command(
+            r#"
+            name: "json.set",
+            desc: "d",
+            args: [
+                ["config", "a config", ArgType::Arg, serde_json::Value, Collection::Unit, None, schema: Schema::of::<my_module::Config>()],
+                ["configs", "some configs", ArgType::Arg, serde_json::Value, Collection::Unit, None, schema: Schema::of::<Vec<my_module::Config>>()],
+                ["meta", "metadata", ArgType::Arg, serde_json::Value, Collection::Unit, None],
+                ["point", "a point", ArgType::Arg, std::primitive::f64, Collection::Unit, None],
+            ],
+            "#,
+        );
+        let data_types: Vec<&str> = cmd.args.iter().map(|a| a.data_type.as_str()).collect();
+        assert_eq!(
+            data_types,
+            ["JSON<Config>", "JSON<Vec<Config>>", "JSON", "f64"]
+        );
+    }
+
+    fn command_names(attr: &str, item: &str) -> Result<Vec<String>, String> {
+        let attr = attr.parse().unwrap();
+        match commands(attr, item.parse().unwrap()) {
+            Ok(cmds) => Ok(cmds.into_iter().map(|c| c.name).collect()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    #[test]
+    fn commands_test() {
+        let body = |name: &str| format!("name: \"{}\", desc: \"d\", args: []", name);
+
+        let item = format!("static CMD: Command = command!{{ {} }};", body("a"));
+        assert_eq!(command_names("", &item), Ok(vec!["a".to_owned()]));
+        assert_eq!(command_names("clean", &item), Ok(vec!["a".to_owned()]));
+
+        // commands bound with let in a fn, or nested in other macros
+        let item = format!(
+            "fn cmds() -> Vec<Command> {{ let a = command!{{ {} }}; vec![a, command!{{ {} }}] }}",
+            body("a"),
+            body("b")
+        );
+        assert_eq!(
+            command_names("", &item),
+            Ok(vec!["a".to_owned(), "b".to_owned()])
+        );
+        let item = format!(
+            "fn cmd() -> Result<Command, RedisError> {{ try_command!{{ {} }} }}",
+            body("a")
+        );
+        assert_eq!(command_names("", &item), Ok(vec!["a".to_owned()]));
+
+        assert_eq!(
+            command_names("", "fn cmd() -> Command { Command::new(\"a\", \"d\") }"),
+            Err("rediscmd_doc expects an item containing a `command!` invocation".to_owned())
+        );
+        assert_eq!(
+            command_names("foo", &item),
+            Err("rediscmd_doc takes no arguments".to_owned())
+        );
+        let item = "static CMD: Command = command!{ name: \"a\", args: [] };";
+        assert_eq!(command_names("", item), Err("expected `desc`".to_owned()));
+        let item = "static CMD: Command = command!{ name: NAME, desc: \"d\", args: [] };";
+        assert_eq!(
+            command_names("", item),
+            Err("expected a string literal".to_owned())
+        );
+    }
+
+    #[test]
+    fn default_value_test() {
+        let default = |expr: &str, data_type: &str, key_type: Option<&str>| {
+            default_value(&syn::parse_str(expr).unwrap(), data_type, key_type)
+        };
+
+        assert_eq!(default("Some(Box::new(-2))", "f64", None), "-2.0");
+        assert_eq!(
+            default("Some(Box::new(String::from(\"a\")))", "String", None),
+            "\"a\""
+        );
+        assert_eq!(
+            default("Some(Box::new(MemorySize(3145728)))", "MemorySize", None),
+            "3mb"
+        );
+        assert_eq!(
+            default("Some(Box::new(Longitude(13.0)))", "Longitude", None),
+            "13"
+        );
+        assert_eq!(
+            default("Some(Box::new(LexBound::PosInfinity))", "LexBound", None),
+            "+"
+        );
+        assert_eq!(
+            default(
+                "Some(Box::new(vec![Box::new(1) as Box<dyn Value>, Box::new(2.5)]))",
+                "f64",
+                None
+            ),
+            "[1.0, 2.5]"
+        );
+        assert_eq!(
+            default(
+                "Some(Box::new(Set { values: vec![Box::new(\"a\".to_owned()) as Box<dyn Value>], duplicates: false }))",
+                "String",
+                None
+            ),
+            "[\"a\"]"
+        );
+        assert_eq!(
+            default(
+                "Some(Box::new(vec![(Box::new(1_u64) as Box<dyn Value>, Box::new(\"x\".to_owned()) as Box<dyn Value>)]))",
+                "String",
+                Some("u64")
+            ),
+            "[(1, \"x\")]"
+        );
+
+        // other defaults are shown as written
+        assert_eq!(default("dflt()", "u64", None), "dflt ()");
+    }
+
+    #[test]
+    fn write_section_test() {
+        let dir = env::temp_dir().join(format!("rediscmd_doc_{}", process::id()));
+        let _ = remove_dir_all(&dir);
+        let section = |file: &str| read_to_string(dir.join(file)).unwrap();
+        let files = || -> Vec<String> {
+            let mut files: Vec<String> = read_dir(&dir)
+                .unwrap()
+                .map(|e| e.unwrap().file_name().into_string().unwrap())
+                .collect();
+            files.sort();
+            files
+        };
+
+        write_section(&dir, "b.cmd", "b").unwrap();
+        write_section(&dir, "A.cmd", "a").unwrap();
+        assert_eq!(files(), vec!["a.cmd.md", "b.cmd.md"]);
+
+        // expanding one command again, e.g. in rust-analyzer, leaves the
+        // other ones alone
+        write_section(&dir, "b.cmd", "b2").unwrap();
+        assert_eq!(files(), vec!["a.cmd.md", "b.cmd.md"]);
+        assert_eq!(section("a.cmd.md"), "a");
+        assert_eq!(section("b.cmd.md"), "b2");
+
+        write_section(&dir, "c cmd", "c").unwrap();
+        assert_eq!(files(), vec!["a.cmd.md", "b.cmd.md", "c_cmd.md"]);
+
+        remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,254 @@
+//! Compile time checks of `command!` definitions.
+
+use std::collections::{HashMap, HashSet};
+
+use quote::{quote, ToTokens};
+use syn::{Expr, Lit, LitFloat, LitInt, Type, UnOp};
+
+use crate::def::{lit_int, lit_str, strip_groups, variant, CommandDef};
+
+/// Options of args, see `ArgDef::options`.
+const OPTIONS: &[&str] = &["duplicates", "max_len", "schema", "token"];
+
+/// Checks the definition for mistakes that would otherwise only show up when
+/// parsing args at runtime, returning statements that type check the default
+/// values:
+///
+/// * arg names must be unique, as they are the keys of the parse result
+/// * keywords must be lowercase, as they are matched in lowercase, and
+///   kwargs sharing a keyword must all be optional or all required
+/// * optional positional args must be single values, as e.g. the count of a
+///   `Vec` can't be told apart from the value of a following arg, so later
+///   args can't be reached without it
+/// * with `KwargPolicy::Positional`, keywords only follow all optional
+///   positional args, so there can't be any if a kwarg is required
+/// * maps need a key type, as in `Collection::Map(String)`, and arrays a
+///   length of at least 1, as in `Collection::Array(3)`
+/// * literal values of `Vec`, `Set` and `Array` defaults, and literal keys
+///   and values of `Map` defaults, spelled out in a `vec!`, must have the
+///   declared type. Defaults of single values spelled out as
+///   `Some(Box::new(..))` are bound with the declared type by `argument!`
+///   instead
+/// * options must be known, and apply to the kind of arg
+///
+/// Args that aren't spelled out as literals are skipped, e.g. defaults
+/// returned by a helper fn.
+pub fn validate(def: &CommandDef) -> syn::Result<proc_macro2::TokenStream> {
+    let mut errors: Vec<syn::Error> = Vec::new();
+    let mut names = HashSet::new();
+    let mut keywords: HashMap<String, bool> = HashMap::new();
+    let mut checks = Vec::new();
+
+    let positional_policy = def
+        .kwarg_policy
+        .as_ref()
+        .is_some_and(|p| variant(p).as_deref() == Some("Positional"));
+    let required_kwarg = def
+        .args
+        .iter()
+        .find(|a| variant(&a.arg_type).as_deref() == Some("Kwarg") && !a.is_optional());
+
+    for arg in def.args.iter() {
+        let arg_type = variant(&arg.arg_type);
+        let kind = variant(&arg.kind);
+
+        if let Some(name) = lit_str(&arg.name) {
+            if !names.insert(name.clone()) {
+                errors.push(syn::Error::new_spanned(
+                    &arg.name,
+                    format!("duplicate arg name `{}`", name),
+                ));
+            }
+        }
+
+        // the keyword of a kwarg is its name unless set with `token`
+        let token_expr = arg.option("token").unwrap_or(&arg.name);
+        if arg_type.as_deref() == Some("Kwarg") {
+            if let Some(token) = lit_str(token_expr) {
+                if token != token.to_lowercase() {
+                    errors.push(syn::Error::new_spanned(
+                        token_expr,
+                        format!(
+                            "keyword `{}` can never be matched, keywords are matched in lowercase",
+                            token
+                        ),
+                    ));
+                }
+                match keywords.get(&token) {
+                    Some(optional) if *optional != arg.is_optional() => {
+                        errors.push(syn::Error::new(
+                            arg.span,
+                            format!(
+                                "kwargs sharing the keyword `{}` must all be optional or all required",
+                                token
+                            ),
+                        ));
+                    }
+                    _ => {
+                        keywords.insert(token, arg.is_optional());
+                    }
+                }
+            }
+        } else if arg.option("token").is_some() {
+            errors.push(syn::Error::new(
+                arg.span,
+                "`token` only applies to `ArgType::Kwarg` args",
+            ));
+        }
+
+        for (option, _) in arg.options.iter() {
+            if !OPTIONS.iter().any(|o| option == o) {
+                errors.push(syn::Error::new_spanned(
+                    option,
+                    format!(
+                        "unknown option `{}`, expected one of {}",
+                        option,
+                        OPTIONS.join(", ")
+                    ),
+                ));
+            }
+        }
+        if arg.option("max_len").is_some() && matches!(kind.as_deref(), Some("Unit" | "Array")) {
+            errors.push(syn::Error::new(
+                arg.span,
+                "`max_len` only applies to `Collection::Vec`, `Collection::Set` and `Collection::Map` args",
+            ));
+        }
+        if arg.option("duplicates").is_some() && !matches!(kind.as_deref(), Some("Set" | "Map")) {
+            errors.push(syn::Error::new(
+                arg.span,
+                "`duplicates` only applies to `Collection::Set` and `Collection::Map` args",
+            ));
+        }
+        match kind.as_deref() {
+            Some("Map") if arg.kind_arg().is_none() => {
+                errors.push(syn::Error::new_spanned(
+                    &arg.kind,
+                    "expected a key type, e.g. `Collection::Map(String)`",
+                ));
+            }
+            Some("Array") if arg.kind_arg().and_then(lit_int).as_deref() == Some("0") => {
+                errors.push(syn::Error::new_spanned(
+                    &arg.kind,
+                    "arrays need at least one value",
+                ));
+            }
+            _ => (),
+        }
+
+        if arg.option("schema").is_some() && !arg.is_json() {
+            errors.push(syn::Error::new(
+                arg.span,
+                "`schema` only applies to `serde_json::Value` args",
+            ));
+        }
+
+        if !arg.is_optional() {
+            continue;
+        }
+
+        if let (true, Some("Arg"), Some(kwarg)) =
+            (positional_policy, arg_type.as_deref(), required_kwarg)
+        {
+            errors.push(syn::Error::new(
+                arg.span,
+                format!(
+                    "optional positional args can't be left out before the required kwarg `{}` with `KwargPolicy::Positional`, use `KwargPolicy::Terminator` instead",
+                    lit_str(&kwarg.name).unwrap_or_default()
+                ),
+            ));
+        }
+
+        match (arg_type.as_deref(), kind.as_deref()) {
+            (Some("Arg"), Some(kind)) if kind != "Unit" => {
+                errors.push(syn::Error::new(
+                    arg.span,
+                    format!(
+                        "optional positional args can't be `Collection::{}`, use `ArgType::Kwarg` instead",
+                        kind
+                    ),
+                ));
+            }
+            (_, Some("Vec" | "Set" | "Array")) => {
+                for value in arg.default_values() {
+                    checks.extend(check_value(&arg.data_type, &value));
+                }
+            }
+            (_, Some("Map")) => {
+                let key_type = match arg.kind_arg() {
+                    Some(key) => syn::parse2::<Type>(key.to_token_stream()).ok(),
+                    None => None,
+                };
+                for pair in arg.default_values() {
+                    if let (Expr::Tuple(pair), Some(key_type)) = (strip_groups(&pair), &key_type) {
+                        if pair.elems.len() == 2 {
+                            checks.extend(check_value(key_type, &pair.elems[0]));
+                            checks.extend(check_value(&arg.data_type, &pair.elems[1]));
+                        }
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let mut errors = errors.into_iter();
+    match errors.next() {
+        Some(mut err) => {
+            for e in errors {
+                err.combine(e);
+            }
+            Err(err)
+        }
+        None => Ok(quote! {
+            {
+                #(#checks)*
+            }
+        }),
+    }
+}
+
+/// Checks that a value of a collection default, spelled out as
+/// `Box::new(literal)` and usually cast to `Box<dyn Value>`, has type `ty`.
+/// Unlike a literal default of a single value, the literal doesn't get the
+/// declared type, so it is checked with the type it has in the `vec!`, e.g.
+/// `i32` for `1`.
+fn check_value(ty: &Type, value: &Expr) -> Option<proc_macro2::TokenStream> {
+    let mut value = strip_groups(value);
+    if let Expr::Cast(cast) = value {
+        value = strip_groups(&cast.expr);
+    }
+    let arg = match value {
+        Expr::Call(call) if call.args.len() == 1 => {
+            let path = call.func.to_token_stream().to_string().replace(' ', "");
+            if !path.ends_with("Box::new") {
+                return None;
+            }
+            strip_groups(&call.args[0])
+        }
+        _ => return None,
+    };
+    let (neg, lit) = match arg {
+        Expr::Lit(l) => (None, &l.lit),
+        Expr::Unary(u) if matches!(u.op, UnOp::Neg(_)) => match strip_groups(&u.expr) {
+            Expr::Lit(l) => (Some(u.op), &l.lit),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let lit = match lit {
+        Lit::Int(i) if i.suffix().is_empty() => {
+            Lit::Int(LitInt::new(&format!("{}i32", i.base10_digits()), i.span()))
+        }
+        Lit::Float(f) if f.suffix().is_empty() => Lit::Float(LitFloat::new(
+            &format!("{}f64", f.base10_digits()),
+            f.span(),
+        )),
+        lit => lit.clone(),
+    };
+
+    // never called, only makes the compiler check the type
+    Some(quote! {
+        let _ = || -> ::std::boxed::Box<#ty> { ::std::boxed::Box::new(#neg #lit) };
+    })
+}